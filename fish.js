@@ -0,0 +1,82 @@
+// Controls the simulation from the page, see src/host.rs for the exported functions.
+var fish = {
+    ready: false,
+
+    writeInput: function (text) {
+        var bytes = new TextEncoder().encode(text);
+        var ptr = wasm_exports.fish_input(bytes.length);
+        new Uint8Array(wasm_memory.buffer, ptr, bytes.length).set(bytes);
+    },
+
+    readOutput: function (length) {
+        var ptr = wasm_exports.fish_output();
+        return new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, length));
+    },
+
+    // rule is one of describe().rules, returns false when it is unknown
+    setWeight: function (flockId, rule, weight) {
+        this.writeInput(rule);
+        return wasm_exports.fish_set_weight(flockId, weight) == 1;
+    },
+
+    // positions are in world coordinates, whose size is describe().world_size
+    spawn: function (archetype, x, y) {
+        this.writeInput(archetype);
+        wasm_exports.fish_spawn(x, y);
+    },
+
+    removeNear: function (x, y, radius) {
+        wasm_exports.fish_remove_near(x, y, radius);
+    },
+
+    setPaused: function (paused) {
+        wasm_exports.fish_set_paused(paused ? 1 : 0);
+    },
+
+    // metrics of every flock, empty until the frame after the first call
+    metrics: function () {
+        return JSON.parse(this.readOutput(wasm_exports.fish_metrics()));
+    },
+
+    // rule names, the world size, and the archetypes with their flock and rule weights
+    describe: function () {
+        return JSON.parse(this.readOutput(wasm_exports.fish_describe()));
+    },
+
+    // link opening the simulation with the current seed, counts and rule weights,
+    // null until the simulation started
+    shareLink: function () {
+        var code = this.readOutput(wasm_exports.fish_share());
+        if (!code) {
+            return null;
+        }
+        return location.origin + location.pathname + "#scene=" + code;
+    },
+
+    // share code of the page URL, from "#scene=CODE" or "?scene=CODE"
+    sharedScene: function () {
+        var hash = new URLSearchParams(location.hash.slice(1));
+        var query = new URLSearchParams(location.search);
+        return hash.get("scene") || query.get("scene");
+    }
+};
+
+miniquad_add_plugin({
+    name: "fish",
+    version: 1,
+    register_plugin: function (importObject) {},
+    // runs before the viewer starts, so a shared scene is opened from the first frame
+    on_init: function () {
+        var code = fish.sharedScene();
+        if (code) {
+            fish.writeInput(code);
+            if (wasm_exports.fish_set_share() != 1) {
+                console.warn("ignoring the invalid shared scene in the URL");
+            }
+        }
+        fish.ready = true;
+        if (fish.onReady) {
+            fish.onReady();
+        }
+    }
+});
@@ -0,0 +1,107 @@
+//! Animation state machine of the boids: the clip played follows how a boid moves and what it is busy with.
+//! Swimming is the resting state, a boid turning sharply plays `turn` and a boid hunting or fleeing
+//! plays `dash` once it goes fast or speeds up hard. Clips that play once (a turn, a bite) are never cut short.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::boid::Behavior;
+use crate::movement::Movement;
+use crate::render::Animation;
+
+// speed at which clips of boids without an animator play at their normal rate
+const DEFAULT_REFERENCE_SPEED: f32 = 100.0;
+
+/// Thresholds of the state machine of an archetype.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Animator {
+    /// Speed at which clips play at their normal rate, they play faster or slower in proportion.
+    pub reference_speed: f32,
+    /// Change of heading, in radians per second, above which `turn` plays.
+    pub turn_rate: f32,
+    /// Speed above which a hunting or fleeing boid dashes.
+    pub dash_speed: f32,
+    /// Change of velocity per second above which a hunting or fleeing boid dashes.
+    pub dash_acceleration: f32
+}
+
+/// What the state machine of a boid measured on the previous tick.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AnimatorState {
+    /// Velocity on the previous tick.
+    pub last_vel: Option<Vec2>
+}
+
+impl Animator {
+    /// Clip for a boid moving at `vel`, `elapsed` seconds after the previous tick where it moved at `last_vel`.
+    fn clip(&self, vel: Vec2, last_vel: Option<Vec2>, behavior: Behavior, elapsed: f32) -> &'static str {
+        let Some(last_vel) = last_vel.filter(|_last_vel| elapsed > 0.0) else {
+            return "swim";
+        };
+        let acceleration = (vel - last_vel).length() / elapsed;
+        // headings of (almost) stationary boids are meaningless
+        let turn_rate = if vel.length() > 1.0 && last_vel.length() > 1.0 {
+            last_vel.angle_between(vel).abs() / elapsed
+        } else {
+            0.0
+        };
+
+        if behavior != Behavior::Idle && (vel.length() >= self.dash_speed || acceleration >= self.dash_acceleration) {
+            "dash"
+        } else if turn_rate >= self.turn_rate {
+            "turn"
+        } else {
+            "swim"
+        }
+    }
+}
+
+pub struct Animated<'a> {
+    pub animation: &'a mut Animation,
+    pub animator: Option<&'a Animator>,
+    /// Kept by boids with an animator.
+    pub state: Option<&'a mut AnimatorState>,
+    pub movement: &'a Movement,
+    pub behavior: Behavior
+}
+
+/// Picks the clips and sets the animation speeds, boids without an animator only get the speed.
+pub fn animator_system(input: &mut Vec<Animated>, elapsed: f32) {
+    for animated in input {
+        let vel = animated.movement.vel;
+        let reference_speed = animated.animator.as_ref().map(|a| a.reference_speed).unwrap_or(DEFAULT_REFERENCE_SPEED);
+        animated.animation.speed = vel.length() / reference_speed;
+
+        let (Some(animator), Some(state)) = (animated.animator, animated.state.as_deref_mut()) else {
+            continue;
+        };
+        if animated.animation.is_interruptible() {
+            animated.animation.play(animator.clip(vel, state.last_vel, animated.behavior, elapsed));
+        }
+        state.last_vel = Some(vel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_follows_motion_and_behavior() {
+        let animator = Animator {
+            reference_speed: 100.0,
+            turn_rate: 3.0,
+            dash_speed: 125.0,
+            dash_acceleration: 400.0
+        };
+        let (last_vel, tick) = (Some(vec2(100.0, 0.0)), 0.1);
+        assert_eq!(animator.clip(vec2(100.0, 0.0), last_vel, Behavior::Idle, tick), "swim");
+        // a quarter turn in a tenth of a second
+        assert_eq!(animator.clip(vec2(0.0, 100.0), last_vel, Behavior::Idle, tick), "turn");
+        // fast and speeding up, but only dashing when there is a reason to
+        assert_eq!(animator.clip(vec2(150.0, 0.0), last_vel, Behavior::Idle, tick), "swim");
+        assert_eq!(animator.clip(vec2(150.0, 0.0), last_vel, Behavior::Fleeing, tick), "dash");
+        assert_eq!(animator.clip(vec2(110.0, 0.0), last_vel, Behavior::Hunting, tick / 10.0), "dash");
+        assert_eq!(animator.clip(vec2(150.0, 0.0), None, Behavior::Hunting, tick), "swim");
+    }
+}
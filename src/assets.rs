@@ -0,0 +1,128 @@
+//! Loading of the spritesheets. With the `embed-assets` feature they are compiled into the binary,
+//! otherwise they are read from `resources/` in the working directory, then next to the executable.
+//! A spritesheet that cannot be loaded is replaced by a placeholder, so the viewer always starts.
+
+use std::fmt;
+use std::path::Path;
+use macroquad::prelude::*;
+
+pub const FISH_SPRITESHEET: &str = "fish_spritesheet.png";
+pub const SHARK_SPRITESHEET: &str = "shark_spritesheet.png";
+
+// side of a placeholder tile, in pixels
+const PLACEHOLDER_TILE: u32 = 16;
+
+#[derive(Debug)]
+pub enum AssetError {
+    /// None of the paths could be read.
+    NotFound {
+        name: String,
+        tried: Vec<String>
+    },
+    /// The file is not an image.
+    Decode {
+        name: String,
+        message: String
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::NotFound { name, tried } => write!(f, "could not find {} (tried {})", name, tried.join(", ")),
+            AssetError::Decode { name, message } => write!(f, "could not decode {}: {}", name, message)
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+#[cfg(feature = "embed-assets")]
+fn embedded(name: &str) -> Option<&'static [u8]> {
+    match name {
+        FISH_SPRITESHEET => Some(include_bytes!("../resources/fish_spritesheet.png")),
+        SHARK_SPRITESHEET => Some(include_bytes!("../resources/shark_spritesheet.png")),
+        _ => None
+    }
+}
+
+#[cfg(not(feature = "embed-assets"))]
+fn embedded(_name: &str) -> Option<&'static [u8]> {
+    None
+}
+
+fn paths(name: &str) -> Vec<String> {
+    let mut paths = vec![format!("resources/{}", name)];
+    // not available on the web, where the page serves the resources
+    if let Some(dir) = std::env::current_exe().ok().as_deref().and_then(Path::parent) {
+        paths.push(dir.join("resources").join(name).to_string_lossy().into_owned());
+    }
+    paths
+}
+
+async fn read(name: &str) -> Result<Vec<u8>, AssetError> {
+    if let Some(bytes) = embedded(name) {
+        return Ok(bytes.to_vec());
+    }
+    let tried = paths(name);
+    for path in &tried {
+        if let Ok(bytes) = load_file(path).await {
+            return Ok(bytes);
+        }
+    }
+    Err(AssetError::NotFound { name: name.to_string(), tried })
+}
+
+pub async fn load_texture(name: &str) -> Result<Texture2D, AssetError> {
+    let bytes = read(name).await?;
+    let image = Image::from_file_with_format(&bytes, None).map_err(|error| AssetError::Decode {
+        name: name.to_string(),
+        message: match error {
+            macroquad::Error::ImageError(error) => error.to_string(),
+            error => error.to_string()
+        }
+    })?;
+    Ok(Texture2D::from_image(&image))
+}
+
+/// Loads a spritesheet of `tiles` columns and rows, falling back to `placeholder_image` in `color`.
+pub async fn load_spritesheet(name: &str, tiles: Vec2, color: Color) -> Texture2D {
+    load_texture(name).await.unwrap_or_else(|error| {
+        warn!("{}, drawing placeholders instead", error);
+        Texture2D::from_image(&placeholder_image(tiles, color))
+    })
+}
+
+/// Spritesheet with a triangle of `color` in every tile, pointing down like the sprites of the sheets.
+pub fn placeholder_image(tiles: Vec2, color: Color) -> Image {
+    let (width, height) = (tiles.x as u32 * PLACEHOLDER_TILE, tiles.y as u32 * PLACEHOLDER_TILE);
+    let mut image = Image::gen_image_color(width as u16, height as u16, BLANK);
+    let tile = PLACEHOLDER_TILE as f32;
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = ((x % PLACEHOLDER_TILE) as f32 + 0.5, (y % PLACEHOLDER_TILE) as f32 + 0.5);
+            // the base spans the top of the tile, the tip is at the bottom
+            if (u - tile / 2.0).abs() <= (tile - v) / 2.0 {
+                image.set_pixel(x, y, color);
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholder_draws_a_triangle_per_tile() {
+        let image = placeholder_image(vec2(3.0, 2.0), ORANGE);
+        assert_eq!((image.width, image.height), (48, 32));
+        for (tile_x, tile_y) in [(0, 0), (2, 1)] {
+            let (x, y) = (tile_x * PLACEHOLDER_TILE, tile_y * PLACEHOLDER_TILE);
+            assert_eq!(image.get_pixel(x + 8, y + 1).a, 1.0);
+            assert_eq!(image.get_pixel(x + 8, y + 14).a, 1.0);
+            assert_eq!(image.get_pixel(x + 1, y + 14).a, 0.0);
+        }
+    }
+}
@@ -1,54 +1,308 @@
 use std::collections::HashSet;
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::energy::Energy;
+use crate::{obstacle, rng};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
     pub bounds_margin: f32,
-    pub neighbor_distance: f32,
-    pub separation_distance: f32,
-    pub avoidance_distance: f32,
+    pub neighborhood: Neighborhood,
+    pub separation_perception: Perception,
+    pub cohesion_perception: Perception,
+    pub alignment_perception: Perception,
+    pub avoidance_perception: Perception,
+    pub hunt_perception: Perception,
+    pub food_perception: Perception,
+    pub separation_falloff: Falloff,
+    pub avoidance_falloff: Falloff,
     pub separation_rule_weight: f32,
     pub cohesion_rule_weight: f32,
     pub alignment_rule_weight: f32,
     pub bounds_rule_weight: f32,
     pub exploration_rule_weight: f32,
     pub avoidance_rule_weight: f32,
-    pub field_of_view: f32,
+    pub hunt_rule_weight: f32,
+    pub food_rule_weight: f32,
+    pub occlusion: Option<Occlusion>,
+    /// Top speed, only reached while hunting or fleeing.
     pub max_speed: f32,
+    /// Top speed while roaming around.
+    pub cruise_speed: f32,
+    pub min_speed: f32,
+    /// Largest change of velocity per second.
+    pub max_force: f32,
+    /// Largest change of heading in radians per second.
+    pub max_turn_rate: f32,
     pub flock_id: u8,
-    pub flock_to_avoid: HashSet<u8>
+    pub flock_to_avoid: HashSet<u8>,
+    pub flock_to_hunt: HashSet<u8>
+}
+
+/// Names of the rules whose weight can be changed with `Config::rule_weight_mut`.
+pub const RULE_NAMES: [&str; 8] = ["separation", "cohesion", "alignment", "bounds", "exploration", "avoidance", "hunt", "food"];
+
+impl Config {
+    pub fn rule_weight(&self, rule: &str) -> Option<f32> {
+        match rule {
+            "separation" => Some(self.separation_rule_weight),
+            "cohesion" => Some(self.cohesion_rule_weight),
+            "alignment" => Some(self.alignment_rule_weight),
+            "bounds" => Some(self.bounds_rule_weight),
+            "exploration" => Some(self.exploration_rule_weight),
+            "avoidance" => Some(self.avoidance_rule_weight),
+            "hunt" => Some(self.hunt_rule_weight),
+            "food" => Some(self.food_rule_weight),
+            _ => None
+        }
+    }
+
+    pub fn rule_weight_mut(&mut self, rule: &str) -> Option<&mut f32> {
+        match rule {
+            "separation" => Some(&mut self.separation_rule_weight),
+            "cohesion" => Some(&mut self.cohesion_rule_weight),
+            "alignment" => Some(&mut self.alignment_rule_weight),
+            "bounds" => Some(&mut self.bounds_rule_weight),
+            "exploration" => Some(&mut self.exploration_rule_weight),
+            "avoidance" => Some(&mut self.avoidance_rule_weight),
+            "hunt" => Some(&mut self.hunt_rule_weight),
+            "food" => Some(&mut self.food_rule_weight),
+            _ => None
+        }
+    }
+
+    /// A copy with the rule weights and speeds randomly scaled by up to `amount` (e.g. `0.1` for ±10%).
+    pub fn mutated(&self, amount: f32) -> Config {
+        let mutate = |value: f32| value * (1.0 + rng::gen_range(-amount, amount));
+        Config {
+            separation_rule_weight: mutate(self.separation_rule_weight),
+            cohesion_rule_weight: mutate(self.cohesion_rule_weight),
+            alignment_rule_weight: mutate(self.alignment_rule_weight),
+            avoidance_rule_weight: mutate(self.avoidance_rule_weight),
+            exploration_rule_weight: mutate(self.exploration_rule_weight),
+            hunt_rule_weight: mutate(self.hunt_rule_weight),
+            food_rule_weight: mutate(self.food_rule_weight),
+            max_speed: mutate(self.max_speed),
+            cruise_speed: mutate(self.cruise_speed),
+            ..self.clone()
+        }
+    }
+
+    /// A copy taking each of the values touched by `mutated` from either parent at random.
+    pub fn crossover(&self, other: &Config) -> Config {
+        let pick = |a: f32, b: f32| if rng::gen_range(0.0, 1.0) < 0.5 { a } else { b };
+        Config {
+            separation_rule_weight: pick(self.separation_rule_weight, other.separation_rule_weight),
+            cohesion_rule_weight: pick(self.cohesion_rule_weight, other.cohesion_rule_weight),
+            alignment_rule_weight: pick(self.alignment_rule_weight, other.alignment_rule_weight),
+            avoidance_rule_weight: pick(self.avoidance_rule_weight, other.avoidance_rule_weight),
+            exploration_rule_weight: pick(self.exploration_rule_weight, other.exploration_rule_weight),
+            hunt_rule_weight: pick(self.hunt_rule_weight, other.hunt_rule_weight),
+            food_rule_weight: pick(self.food_rule_weight, other.food_rule_weight),
+            max_speed: pick(self.max_speed, other.max_speed),
+            cruise_speed: pick(self.cruise_speed, other.cruise_speed),
+            ..self.clone()
+        }
+    }
+}
+
+/// How far and how wide a rule sees. `field_of_view` is the cosine of half the view cone,
+/// so `1.0` only sees straight ahead, `0.0` sees everything ahead and `-1.0` sees all around.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Perception {
+    pub distance: f32,
+    pub field_of_view: f32,
+}
+
+impl Perception {
+    /// The smallest perception seeing everything both perceptions see.
+    fn union(self, other: Perception) -> Perception {
+        Perception {
+            distance: self.distance.max(other.distance),
+            field_of_view: self.field_of_view.min(other.field_of_view),
+        }
+    }
+
+    fn perceives(&self, cur: &Boid, other: &Boid) -> bool {
+        is_neighbor(cur, other, self.distance * self.distance, self.field_of_view)
+    }
+
+    fn perceives_point(&self, cur: &Boid, point: Vec2) -> bool {
+        is_visible(cur, point, self.distance * self.distance, self.field_of_view)
+    }
+
+    /// What this perception keeps of the neighbors `neighborhood` picked.
+    fn within(self, neighborhood: Neighborhood) -> Perception {
+        match neighborhood {
+            Neighborhood::Metric => self,
+            // topological neighbors are picked however far they are, only the view cone narrows them down
+            Neighborhood::Topological { .. } => Perception { distance: f32::INFINITY, ..self }
+        }
+    }
+}
+
+/// Which boids are considered neighbors. Both modes only consider boids inside the widest view cone
+/// of the rules, and each rule then keeps the neighbors within its own view cone (and, in metric mode, distance).
+/// Avoidance and hunting always see the boids within their distance, whatever the mode.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Neighborhood {
+    /// Every boid within the farthest perception distance of the rules.
+    Metric,
+    /// The `k` nearest boids, however far they are.
+    Topological { k: usize },
+}
+
+/// Limits perception to the boids in the line of sight. Obstacles always block the view.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Occlusion {
+    /// How many boids standing in the line of sight block the view, `None` lets boids see through each other.
+    pub blocking_boids: Option<usize>,
+    /// How wide a boid is when it stands in the line of sight.
+    pub boid_radius: f32,
+}
+
+/// How strongly a repulsive rule (separation, avoidance) pushes away from a neighbor
+/// depending on how close it is.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Falloff {
+    /// No falloff: the raw offset is used, so a neighbor at the edge of the radius pushes the hardest.
+    Constant,
+    /// Push of `radius / distance`, i.e. `radius` at the edge and growing as the neighbor gets closer.
+    Inverse,
+    /// Push of `(radius / distance)^2`, i.e. `radius` at the edge and growing quickly as the neighbor gets closer.
+    InverseSquare,
+    /// Push fading linearly from `radius` on contact to nothing at the edge of the radius.
+    Linear,
+}
+
+// keeps the inverse kernels bounded when two boids (almost) overlap
+const MIN_FALLOFF_DISTANCE: f32 = 1.0;
+
+impl Falloff {
+    fn repulsion(self, offset: Vec2, radius: f32) -> Vec2 {
+        let direction = offset.normalize_or_zero();
+        let distance = offset.length().max(MIN_FALLOFF_DISTANCE);
+        match self {
+            Falloff::Constant => offset,
+            Falloff::Inverse => direction * radius * (radius / distance),
+            Falloff::InverseSquare => direction * radius * (radius / distance).powi(2),
+            Falloff::Linear => direction * (radius - distance).max(0.0),
+        }
+    }
+}
+
+/// What a boid is busy with, as decided by `boids_system` on the last tick.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Behavior {
+    #[default]
+    Idle,
+    Hunting,
+    Fleeing,
+    /// Caught by a predator, no longer a boid and only left to play its `eaten` clip.
+    Eaten
 }
 
 pub struct Boid<'a> {
+    /// Id of the entity, to report it.
+    pub id: Option<u64>,
     pub pos: &'a Vec2,
     pub vel: &'a mut Vec2,
-    pub config: &'a Config
+    pub config: &'a Config,
+    pub energy: Option<&'a Energy>,
+    pub behavior: Option<&'a mut Behavior>
+}
+
+impl Boid<'_> {
+    fn vigor(&self) -> f32 {
+        self.energy.map(|e| e.vigor()).unwrap_or(1.0)
+    }
+
+    fn is_hungry(&self) -> bool {
+        self.energy.map(|e| e.is_hungry()).unwrap_or(true)
+    }
 }
 
 
-pub fn boids_system(boids: &mut Vec<Boid>, bounds: &Rect) {
+/// Steers every boid and returns the indices of the boids whose velocity became non-finite.
+/// Those boids are stopped instead of being allowed to poison their neighbors on the next tick.
+pub fn boids_system(boids: &mut [Boid], obstacles: &[Circle], foods: &[Circle], bounds: &Rect, elapsed: f32) -> Vec<usize> {
+    let mut invalid = vec![];
     for i in 0..boids.len() {
         let cur = boids.get(i).unwrap();
-        let neighbor_indices = neighbor_indices(cur, boids, cur.config.neighbor_distance, cur.config.field_of_view);
-        let neighbors = select(&boids, &neighbor_indices);
-        let my_flock: Vec<&Boid> = neighbors.iter().filter(|b| b.config.flock_id == cur.config.flock_id).map(|a| *a).collect();
-        let to_avoids:Vec<&Boid> = neighbors.iter().filter(|b| cur.config.flock_to_avoid.contains(&b.config.flock_id)).map(|a| *a).collect();
-        let acceleration = separation_rule(cur, &neighbors, cur.config.separation_distance, cur.config.separation_rule_weight)
-            + cohesion_rule(cur, &my_flock, cur.config.separation_distance, cur.config.cohesion_rule_weight)
-            + alignment_rule(&my_flock, cur.config.alignment_rule_weight)
+        let config = cur.config;
+        // a single query sees everything any of the rules sees, each rule then narrows it down
+        let perception = config.separation_perception
+            .union(config.cohesion_perception)
+            .union(config.alignment_perception)
+            .union(config.avoidance_perception)
+            .union(config.hunt_perception);
+        // predators and prey are noticed within their distance, not among the nearest flockmates
+        let other_flock_indices = match config.neighborhood {
+            Neighborhood::Metric => None,
+            Neighborhood::Topological { .. } => Some(neighbor_indices(cur, boids, obstacles, Neighborhood::Metric,
+                config.avoidance_perception.union(config.hunt_perception), config.occlusion))
+        };
+        let neighbor_indices = neighbor_indices(cur, boids, obstacles, config.neighborhood, perception, config.occlusion);
+        let neighbors = select(boids, &neighbor_indices);
+        let other_flocks = match &other_flock_indices {
+            Some(indices) => select(boids, indices),
+            None => neighbors.clone()
+        };
+        let my_flock: Vec<&Boid> = neighbors.iter().filter(|b| b.config.flock_id == config.flock_id).copied().collect();
+        let to_avoids:Vec<&Boid> = other_flocks.iter().filter(|b| config.flock_to_avoid.contains(&b.config.flock_id)).copied().collect();
+        let to_avoids = perceived(cur, &to_avoids, config.avoidance_perception, Neighborhood::Metric);
+        let to_hunts: Vec<&Boid> = if cur.is_hungry() {
+            other_flocks.iter().filter(|b| config.flock_to_hunt.contains(&b.config.flock_id)).copied().collect()
+        } else {
+            vec![]
+        };
+        let to_hunts = perceived(cur, &to_hunts, config.hunt_perception, Neighborhood::Metric);
+        let vigor = cur.vigor();
+        let acceleration = separation_rule(cur, &perceived(cur, &neighbors, config.separation_perception, config.neighborhood), config.separation_perception.distance, config.separation_falloff, config.separation_rule_weight)
+            + cohesion_rule(cur, &perceived(cur, &my_flock, config.cohesion_perception, config.neighborhood), config.separation_perception.distance, config.cohesion_rule_weight)
+            + alignment_rule(&perceived(cur, &my_flock, config.alignment_perception, config.neighborhood), config.alignment_rule_weight)
             + bounds_rule(cur, bounds, cur.config.bounds_margin, cur.config.max_speed, cur.config.bounds_rule_weight)
-            + exploration_rule(cur, cur.config.max_speed, cur.config.exploration_rule_weight)
-            + avoidance_rule(cur, &to_avoids, cur.config.avoidance_distance, cur.config.avoidance_rule_weight)
+            + exploration_rule(cur, cur.config.cruise_speed, cur.config.exploration_rule_weight)
+            + avoidance_rule(cur, &to_avoids, config.avoidance_perception.distance, config.avoidance_falloff, config.avoidance_rule_weight)
+            + hunt_rule(cur, &to_hunts, config.max_speed, config.hunt_rule_weight)
+            + food_rule(cur, foods, config.food_perception, config.cruise_speed, config.food_rule_weight)
             ;
+        let acceleration = acceleration * vigor;
+        let behavior = if !to_avoids.is_empty() {
+            Behavior::Fleeing
+        } else if !to_hunts.is_empty() {
+            Behavior::Hunting
+        } else {
+            Behavior::Idle
+        };
+        // bursting to top speed is reserved for chasing prey and escaping predators
+        let speed_limit = if behavior == Behavior::Idle {
+            config.cruise_speed
+        } else {
+            config.max_speed
+        } * vigor;
 
         let cur = boids.get_mut(i).unwrap();
-        *cur.vel += acceleration;
-        *cur.vel = limit_vel(*cur.vel, cur.config.max_speed);
+        let steering = limit_vel(acceleration, cur.config.max_force * elapsed);
+        let vel = limit_turn(*cur.vel, *cur.vel + steering, cur.config.max_turn_rate * elapsed);
+        let vel = limit_vel(vel, speed_limit);
+        let vel = ensure_min_speed(vel, *cur.vel, cur.config.min_speed);
+        if let Some(current) = cur.behavior.as_deref_mut() {
+            *current = behavior;
+        }
+        if vel.is_finite() {
+            *cur.vel = vel;
+        } else {
+            *cur.vel = vec2(0.0, 0.0);
+            invalid.push(i);
+        }
     }
+
+    invalid
 }
 
 fn exploration_rule(cur: &Boid, speed: f32, weight: f32) -> Vec2 {
-    let random_vel = vec2(rand::gen_range(-1.0, 1.0), rand::gen_range(-1.0, 1.0));
+    let random_vel = vec2(rng::gen_range(-1.0, 1.0), rng::gen_range(-1.0, 1.0));
     if random_vel.dot(*cur.vel) > 0.2 {
         random_vel * speed * weight
     } else {
@@ -65,6 +319,32 @@ fn limit_vel(vel: Vec2, speed: f32) -> Vec2 {
     }
 }
 
+fn limit_turn(old_vel: Vec2, new_vel: Vec2, max_angle: f32) -> Vec2 {
+    if old_vel == Vec2::ZERO || new_vel == Vec2::ZERO {
+        return new_vel;
+    }
+
+    let angle = old_vel.angle_between(new_vel);
+    if angle.abs() > max_angle {
+        Vec2::from_angle(max_angle.copysign(angle)).rotate(old_vel.normalize()) * new_vel.length()
+    } else {
+        new_vel
+    }
+}
+
+fn ensure_min_speed(vel: Vec2, old_vel: Vec2, min_speed: f32) -> Vec2 {
+    // non-finite velocities are left for `boids_system` to report
+    if !vel.is_finite() || vel.length() >= min_speed {
+        return vel;
+    }
+
+    // keep going the way we were heading when the steering cancelled the velocity out
+    let heading = vel.try_normalize()
+        .or_else(|| old_vel.try_normalize())
+        .unwrap_or(Vec2::ZERO);
+    heading * min_speed
+}
+
 fn alignment_rule(boids: &Vec<&Boid>, weight: f32) -> Vec2 {
     weight * 
         boids.iter()
@@ -75,9 +355,10 @@ fn alignment_rule(boids: &Vec<&Boid>, weight: f32) -> Vec2 {
 }
 
 fn cohesion_rule(cur: &Boid, boids: &Vec<&Boid>, min_distance: f32, weight: f32) -> Vec2 {
+    let min_distance_squared = min_distance * min_distance;
     weight * 
         boids.iter()
-            .filter(|b| (*b.pos - *cur.pos).length_squared() > min_distance)
+            .filter(|b| (*b.pos - *cur.pos).length_squared() > min_distance_squared)
             .map(|b| (1.0, *b.pos))
             .reduce(|(a1, ad), (b1, bd)| (a1 + b1, ad + bd))
             .map(|(n, d)| d / n)
@@ -85,28 +366,48 @@ fn cohesion_rule(cur: &Boid, boids: &Vec<&Boid>, min_distance: f32, weight: f32)
             .unwrap_or(vec2(0.0, 0.0))
 }
 
-fn separation_rule(cur: &Boid, boids: &Vec<&Boid>, max_distance: f32, weight: f32) -> Vec2 {
-    let max_distance_squared = max_distance * max_distance;
-    weight * 
-        boids.iter()
-            .map(|b| (1.0, *cur.pos - *b.pos))
-            .filter(|(_ignored, d)| d.length_squared() <= max_distance_squared)
-            .reduce(|(a1, ad), (b1, bd)| (a1 + b1, ad + bd))
-            .map(|(n, d)| d / n)
-            .unwrap_or(vec2(0.0, 0.0))
+fn separation_rule(cur: &Boid, boids: &Vec<&Boid>, max_distance: f32, falloff: Falloff, weight: f32) -> Vec2 {
+    repulsion_rule(cur, boids, max_distance, falloff, weight)
 }
 
-fn avoidance_rule(cur: &Boid, boids: &Vec<&Boid>, max_distance: f32, weight: f32) -> Vec2 {
+fn avoidance_rule(cur: &Boid, boids: &Vec<&Boid>, max_distance: f32, falloff: Falloff, weight: f32) -> Vec2 {
+    repulsion_rule(cur, boids, max_distance, falloff, weight)
+}
+
+fn repulsion_rule(cur: &Boid, boids: &Vec<&Boid>, max_distance: f32, falloff: Falloff, weight: f32) -> Vec2 {
     let max_distance_squared = max_distance * max_distance;
     weight * 
         boids.iter()
-            .map(|b| (1.0, *cur.pos - *b.pos))
-            .filter(|(_ignored, d)| d.length_squared() <= max_distance_squared)
+            .map(|b| *cur.pos - *b.pos)
+            .filter(|d| d.length_squared() <= max_distance_squared)
+            .map(|d| (1.0, falloff.repulsion(d, max_distance)))
             .reduce(|(a1, ad), (b1, bd)| (a1 + b1, ad + bd))
             .map(|(n, d)| d / n)
             .unwrap_or(vec2(0.0, 0.0))
 }
 
+fn hunt_rule(cur: &Boid, boids: &[&Boid], speed: f32, weight: f32) -> Vec2 {
+    boids.iter()
+        .map(|b| *b.pos - *cur.pos)
+        .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        .map(|to_prey| to_prey.normalize_or_zero() * speed * weight)
+        .unwrap_or(vec2(0.0, 0.0))
+}
+
+fn food_rule(cur: &Boid, foods: &[Circle], perception: Perception, speed: f32, weight: f32) -> Vec2 {
+    if !cur.is_hungry() {
+        return vec2(0.0, 0.0);
+    }
+
+    foods.iter()
+        .map(|f| f.point())
+        .filter(|f| perception.perceives_point(cur, *f))
+        .map(|f| f - *cur.pos)
+        .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        .map(|to_food| to_food.normalize_or_zero() * speed * weight)
+        .unwrap_or(vec2(0.0, 0.0))
+}
+
 fn bounds_rule(boid: &Boid, bounds: &Rect, margin: f32, speed: f32, weight: f32) -> Vec2 {
     let rect = Rect::new(bounds.x + margin, bounds.y + margin, bounds.w - 2.0 * margin, bounds.h - 2.0 * margin);
     let x = if boid.pos.x < rect.left() {
@@ -128,7 +429,7 @@ fn bounds_rule(boid: &Boid, bounds: &Rect, margin: f32, speed: f32, weight: f32)
     vec2(x, y) * weight
 }
 
-fn select<'a, T>(list: &'a Vec<T>, indices: &'a Vec<usize>) -> Vec<&'a T> {
+fn select<'a, T>(list: &'a [T], indices: &'a Vec<usize>) -> Vec<&'a T> {
     let mut vec: Vec<&T> = vec![];
     for index in indices {
         if let Some(m) = list.get(*index) {
@@ -139,28 +440,391 @@ fn select<'a, T>(list: &'a Vec<T>, indices: &'a Vec<usize>) -> Vec<&'a T> {
     vec
 }
 
-fn neighbor_indices(cur: &Boid, boids: &Vec<Boid>, max_distance: f32, field_of_view: f32) -> Vec<usize> {
-    let max_distance_squared = max_distance * max_distance;
-    boids.iter().enumerate()
-        .filter(|(_idx, other)| is_neighbor(cur, other, max_distance_squared, field_of_view))
-        .map(|(idx, _other)| idx)
+fn perceived<'a, 'b>(cur: &Boid, neighbors: &[&'a Boid<'b>], perception: Perception, neighborhood: Neighborhood) -> Vec<&'a Boid<'b>> {
+    let perception = perception.within(neighborhood);
+    neighbors.iter()
+        .filter(|b| perception.perceives(cur, b))
+        .copied()
         .collect()
 }
 
+fn neighbor_indices(cur: &Boid, boids: &[Boid], obstacles: &[Circle], neighborhood: Neighborhood, perception: Perception, occlusion: Option<Occlusion>) -> Vec<usize> {
+    let max_distance_squared = match neighborhood {
+        Neighborhood::Metric => perception.distance * perception.distance,
+        Neighborhood::Topological { .. } => f32::INFINITY
+    };
+    // a boid that went non-finite is stopped and reported on its own turn, it must not poison the others before it
+    let in_view: Vec<usize> = boids.iter().enumerate()
+        .filter(|(_idx, other)| other.pos.is_finite() && other.vel.is_finite())
+        .filter(|(_idx, other)| is_neighbor(cur, other, max_distance_squared, perception.field_of_view))
+        .map(|(idx, _other)| idx)
+        .collect();
+    let visible = match occlusion {
+        Some(occlusion) => {
+            // nearest first, so each candidate only looks through the boids in front of it
+            let mut by_distance = in_view.clone();
+            by_distance.sort_by(|a, b| cur.pos.distance_squared(*boids[*a].pos).total_cmp(&cur.pos.distance_squared(*boids[*b].pos)));
+            let blockers = select(boids, &by_distance);
+            in_view.iter()
+                .filter(|idx| is_in_line_of_sight(cur, &boids[**idx], &blockers, obstacles, occlusion))
+                .copied()
+                .collect()
+        }
+        None => in_view
+    };
+
+    match neighborhood {
+        Neighborhood::Metric => visible,
+        Neighborhood::Topological { k } => {
+            let mut visible: Vec<(usize, f32)> = visible.into_iter()
+                .map(|idx| (idx, cur.pos.distance_squared(*boids[idx].pos)))
+                .collect();
+            if visible.len() > k {
+                visible.select_nth_unstable_by(k, |(_a, a), (_b, b)| a.total_cmp(b));
+                visible.truncate(k);
+            }
+            visible.into_iter().map(|(idx, _distance)| idx).collect()
+        }
+    }
+}
+
+/// `blockers` are sorted by distance to `cur`.
+fn is_in_line_of_sight(cur: &Boid, other: &Boid, blockers: &[&Boid], obstacles: &[Circle], occlusion: Occlusion) -> bool {
+    if obstacles.iter().any(|o| obstacle::blocks_line_of_sight(o, *cur.pos, *other.pos)) {
+        return false;
+    }
+
+    match occlusion.blocking_boids {
+        Some(blocking_boids) => {
+            let distance_squared = cur.pos.distance_squared(*other.pos);
+            let blocking = blockers.iter()
+                .take_while(|b| cur.pos.distance_squared(*b.pos) < distance_squared)
+                .filter(|b| obstacle::blocks_line_of_sight(&Circle::new(b.pos.x, b.pos.y, occlusion.boid_radius), *cur.pos, *other.pos))
+                .take(blocking_boids)
+                .count();
+            blocking < blocking_boids
+        }
+        None => true
+    }
+}
+
 fn is_neighbor(cur: &Boid, other: &Boid, max_distance_squared: f32, field_of_view: f32) -> bool {
     if std::ptr::eq(cur, other) {
         return false;
     }
 
-    if cur.pos.distance_squared(*other.pos) > max_distance_squared {
+    is_visible(cur, *other.pos, max_distance_squared, field_of_view)
+}
+
+fn is_visible(cur: &Boid, point: Vec2, max_distance_squared: f32, field_of_view: f32) -> bool {
+    if cur.pos.distance_squared(point) > max_distance_squared {
         return false;
     }
 
-    let to_other = *other.pos - *cur.pos;
-    let is_visible_for_me = cur.vel.normalize().dot(to_other.normalize()) > field_of_view;
-    if !is_visible_for_me {
-        return false;
+    // a stationary boid has no heading and a coincident point has no direction,
+    // so both are treated as visible instead of producing NaN from `normalize`
+    let heading = cur.vel.normalize_or_zero();
+    let to_other = (point - *cur.pos).normalize_or_zero();
+    if heading == Vec2::ZERO || to_other == Vec2::ZERO {
+        return true;
+    }
+
+    heading.dot(to_other) >= field_of_view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            bounds_margin: 0.0,
+            neighborhood: Neighborhood::Metric,
+            separation_perception: Perception { distance: 20.0, field_of_view: -0.5 },
+            cohesion_perception: Perception { distance: 200.0, field_of_view: -0.5 },
+            alignment_perception: Perception { distance: 200.0, field_of_view: -0.5 },
+            avoidance_perception: Perception { distance: 80.0, field_of_view: -0.5 },
+            hunt_perception: Perception { distance: 0.0, field_of_view: 1.0 },
+            food_perception: Perception { distance: 0.0, field_of_view: 1.0 },
+            separation_falloff: Falloff::Constant,
+            avoidance_falloff: Falloff::Constant,
+            separation_rule_weight: 0.75,
+            cohesion_rule_weight: 0.01,
+            alignment_rule_weight: 0.03,
+            bounds_rule_weight: 0.0,
+            exploration_rule_weight: 0.0,
+            avoidance_rule_weight: 0.2,
+            hunt_rule_weight: 0.0,
+            food_rule_weight: 0.0,
+            occlusion: None,
+            max_speed: 140.0,
+            cruise_speed: 140.0,
+            min_speed: 0.0,
+            max_force: f32::INFINITY,
+            max_turn_rate: f32::INFINITY,
+            flock_id: 1,
+            flock_to_avoid: Default::default(),
+            flock_to_hunt: Default::default()
+        }
+    }
+
+    #[test]
+    fn cohesion_ignores_boids_within_separation_distance() {
+        let config = config();
+        let (pos_a, mut vel_a) = (vec2(0.0, 0.0), vec2(1.0, 0.0));
+        let (pos_b, mut vel_b) = (vec2(10.0, 0.0), vec2(1.0, 0.0));
+        let cur = Boid { id: None, pos: &pos_a, vel: &mut vel_a, config: &config, energy: None, behavior: None };
+        let other = Boid { id: None, pos: &pos_b, vel: &mut vel_b, config: &config, energy: None, behavior: None };
+
+        // 10 units apart is inside the 20 units separation distance, even though 10^2 > 20
+        assert_eq!(cohesion_rule(&cur, &vec![&other], 20.0, 1.0), vec2(0.0, 0.0));
+        assert_eq!(cohesion_rule(&cur, &vec![&other], 5.0, 1.0), vec2(10.0, 0.0));
+    }
+
+    #[test]
+    fn falloff_kernels_push_harder_when_closer() {
+        let near = vec2(5.0, 0.0);
+        let far = vec2(20.0, 0.0);
+
+        assert_eq!(Falloff::Constant.repulsion(near, 20.0), near);
+        assert_eq!(Falloff::Inverse.repulsion(far, 20.0), vec2(20.0, 0.0));
+        assert_eq!(Falloff::Inverse.repulsion(near, 20.0), vec2(80.0, 0.0));
+        assert_eq!(Falloff::InverseSquare.repulsion(near, 20.0), vec2(320.0, 0.0));
+        assert_eq!(Falloff::Linear.repulsion(near, 20.0), vec2(15.0, 0.0));
+        assert_eq!(Falloff::Linear.repulsion(far, 20.0), vec2(0.0, 0.0));
+        assert_eq!(Falloff::InverseSquare.repulsion(vec2(0.0, 0.0), 20.0), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn stationary_boid_sees_all_around() {
+        let config = config();
+        let (pos_a, mut vel_a) = (vec2(0.0, 0.0), vec2(0.0, 0.0));
+        let (pos_b, mut vel_b) = (vec2(-10.0, 0.0), vec2(1.0, 0.0));
+        let cur = Boid { id: None, pos: &pos_a, vel: &mut vel_a, config: &config, energy: None, behavior: None };
+        let other = Boid { id: None, pos: &pos_b, vel: &mut vel_b, config: &config, energy: None, behavior: None };
+
+        assert!(is_neighbor(&cur, &other, 100.0 * 100.0, 0.9));
+    }
+
+    #[test]
+    fn coincident_boid_is_a_neighbor() {
+        let config = config();
+        let (pos_a, mut vel_a) = (vec2(5.0, 5.0), vec2(1.0, 0.0));
+        let (pos_b, mut vel_b) = (vec2(5.0, 5.0), vec2(0.0, 1.0));
+        let cur = Boid { id: None, pos: &pos_a, vel: &mut vel_a, config: &config, energy: None, behavior: None };
+        let other = Boid { id: None, pos: &pos_b, vel: &mut vel_b, config: &config, energy: None, behavior: None };
+
+        assert!(is_neighbor(&cur, &other, 100.0 * 100.0, 0.9));
+    }
+
+    #[test]
+    fn topological_neighborhood_picks_k_nearest_visible() {
+        let config = config();
+        let positions = [vec2(0.0, 0.0), vec2(500.0, 0.0), vec2(10.0, 0.0), vec2(-5.0, 0.0), vec2(300.0, 0.0)];
+        let mut velocities = [vec2(1.0, 0.0); 5];
+        let boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+
+        // the boid right behind is outside the field of view, the rest are beyond the perception distance
+        let perception = Perception { distance: 200.0, field_of_view: 0.0 };
+        let mut indices = neighbor_indices(&boids[0], &boids, &[], Neighborhood::Topological { k: 2 }, perception, None);
+        indices.sort();
+        assert_eq!(indices, vec![2, 4]);
+    }
+
+    #[test]
+    fn each_rule_narrows_the_shared_query() {
+        let config = config();
+        let positions = [vec2(0.0, 0.0), vec2(-50.0, 0.0), vec2(150.0, 0.0)];
+        let mut velocities = [vec2(1.0, 0.0); 3];
+        let boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+        let all_around = Perception { distance: 100.0, field_of_view: -1.0 };
+        let ahead = Perception { distance: 200.0, field_of_view: 0.0 };
+
+        let indices = neighbor_indices(&boids[0], &boids, &[], Neighborhood::Metric, all_around.union(ahead), None);
+        let neighbors = select(&boids, &indices);
+
+        assert_eq!(indices, vec![1, 2]);
+        assert!(std::ptr::eq(perceived(&boids[0], &neighbors, all_around, Neighborhood::Metric)[0], &boids[1]));
+        assert!(std::ptr::eq(perceived(&boids[0], &neighbors, ahead, Neighborhood::Metric)[0], &boids[2]));
+    }
+
+    #[test]
+    fn mutation_stays_within_its_amount() {
+        let config = config();
+        rng::srand(11);
+        for _i in 0..100 {
+            let mutated = config.mutated(0.1);
+            for rule in RULE_NAMES {
+                let (weight, original) = (mutated.rule_weight(rule).unwrap(), config.rule_weight(rule).unwrap());
+                assert!((weight - original).abs() <= original.abs() * 0.1 + f32::EPSILON);
+            }
+            assert!((mutated.max_speed - config.max_speed).abs() <= config.max_speed * 0.1 + 1e-4);
+            assert_eq!((mutated.min_speed, mutated.flock_id), (config.min_speed, config.flock_id));
+        }
+        assert_eq!(config.mutated(0.0).cohesion_rule_weight, config.cohesion_rule_weight);
+    }
+
+    #[test]
+    fn crossover_takes_every_value_from_a_parent() {
+        let mother = config();
+        let father = Config { cohesion_rule_weight: 1.0, max_speed: 50.0, min_speed: 10.0, flock_id: 2, ..config() };
+        rng::srand(4);
+        let children: Vec<Config> = (0..50).map(|_i| mother.crossover(&father)).collect();
+        for child in &children {
+            assert!([mother.cohesion_rule_weight, father.cohesion_rule_weight].contains(&child.cohesion_rule_weight));
+            assert!([mother.max_speed, father.max_speed].contains(&child.max_speed));
+            assert_eq!((child.min_speed, child.flock_id), (mother.min_speed, mother.flock_id));
+        }
+        assert!(children.iter().any(|c| c.max_speed == father.max_speed));
+        assert!(children.iter().any(|c| c.max_speed == mother.max_speed));
+    }
+
+    #[test]
+    fn hunt_chases_the_nearest_prey() {
+        let config = config();
+        let positions = [vec2(0.0, 0.0), vec2(0.0, 30.0), vec2(-10.0, 0.0)];
+        let mut velocities = [vec2(1.0, 0.0); 3];
+        let boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+
+        assert_eq!(hunt_rule(&boids[0], &[&boids[1], &boids[2]], 100.0, 0.5), vec2(-50.0, 0.0));
+        assert_eq!(hunt_rule(&boids[0], &[], 100.0, 0.5), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn topological_rules_keep_neighbors_beyond_their_distance() {
+        let config = config();
+        let positions = [vec2(0.0, 0.0), vec2(60.0, 0.0), vec2(-60.0, 0.0)];
+        let mut velocities = [vec2(1.0, 0.0); 3];
+        let boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+        let neighbors = vec![&boids[1], &boids[2]];
+
+        // both are three times the separation distance away, only the one behind is out of view
+        let perception = Perception { distance: 20.0, field_of_view: 0.0 };
+        assert!(perceived(&boids[0], &neighbors, perception, Neighborhood::Metric).is_empty());
+        let perceived = perceived(&boids[0], &neighbors, perception, Neighborhood::Topological { k: 2 });
+        assert_eq!(perceived.len(), 1);
+        assert!(std::ptr::eq(perceived[0], &boids[1]));
     }
 
-    return true;
-}
\ No newline at end of file
+    #[test]
+    fn topological_boids_flee_and_hunt_only_within_their_distance() {
+        let fish = Config {
+            neighborhood: Neighborhood::Topological { k: 1 },
+            flock_id: 0,
+            flock_to_avoid: HashSet::from([1]),
+            ..config()
+        };
+        let shark = Config {
+            neighborhood: Neighborhood::Topological { k: 1 },
+            hunt_perception: Perception { distance: 100.0, field_of_view: -1.0 },
+            flock_to_hunt: HashSet::from([0]),
+            ..config()
+        };
+        let behaviors_with_shark_at = |x: f32| {
+            // the flockmate is the nearest boid of the fish, which only flees the shark within 80 units
+            let positions = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(x, 0.0)];
+            let mut velocities = [vec2(1.0, 0.0); 3];
+            let mut behaviors = [Behavior::Idle; 3];
+            let mut boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut()).zip(behaviors.iter_mut()).enumerate()
+                .map(|(i, ((pos, vel), behavior))| Boid { id: None, pos, vel, config: if i == 2 { &shark } else { &fish }, energy: None, behavior: Some(behavior) })
+                .collect();
+            boids_system(&mut boids, &[], &[], &Rect::new(-1000.0, -1000.0, 2000.0, 2000.0), 0.1);
+            drop(boids);
+            (behaviors[0], behaviors[2])
+        };
+
+        assert_eq!(behaviors_with_shark_at(50.0), (Behavior::Fleeing, Behavior::Hunting));
+        assert_eq!(behaviors_with_shark_at(500.0), (Behavior::Idle, Behavior::Idle));
+    }
+
+    #[test]
+    fn occluded_boids_are_not_neighbors() {
+        let config = config();
+        let positions = [vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(50.0, 0.0), vec2(0.0, 100.0)];
+        let mut velocities = [vec2(1.0, 1.0); 4];
+        let boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+        let rock = [Circle::new(0.0, 50.0, 10.0)];
+        let occlusion = Occlusion { blocking_boids: Some(1), boid_radius: 5.0 };
+
+        let perception = Perception { distance: 200.0, field_of_view: -1.0 };
+        let mut indices = neighbor_indices(&boids[0], &boids, &rock, Neighborhood::Metric, perception, None);
+        indices.sort();
+        assert_eq!(indices, vec![1, 2, 3]);
+
+        // the rock hides boid 3 and boid 2 stands between us and boid 1
+        let indices = neighbor_indices(&boids[0], &boids, &rock, Neighborhood::Metric, perception, Some(occlusion));
+        assert_eq!(indices, vec![2]);
+    }
+
+    #[test]
+    fn turn_rate_keeps_speed_but_limits_heading_change() {
+        let vel = limit_turn(vec2(10.0, 0.0), vec2(-20.0, 0.0), 0.5);
+
+        assert!((vel.length() - 20.0).abs() < 1e-4);
+        assert!((vec2(10.0, 0.0).angle_between(vel).abs() - 0.5).abs() < 1e-4);
+        assert_eq!(limit_turn(vec2(10.0, 0.0), vec2(10.0, 1.0), 0.5), vec2(10.0, 1.0));
+    }
+
+    #[test]
+    fn min_speed_keeps_the_previous_heading() {
+        assert_eq!(ensure_min_speed(vec2(0.0, 0.0), vec2(0.0, 5.0), 10.0), vec2(0.0, 10.0));
+        assert_eq!(ensure_min_speed(vec2(3.0, 0.0), vec2(0.0, 5.0), 10.0), vec2(10.0, 0.0));
+        assert_eq!(ensure_min_speed(vec2(0.0, 0.0), vec2(0.0, 0.0), 10.0), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn stationary_and_coincident_boids_stay_finite() {
+        let config = config();
+        let positions = [vec2(50.0, 50.0), vec2(50.0, 50.0), vec2(60.0, 50.0)];
+        let mut velocities = [vec2(0.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 0.0)];
+        let mut boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+
+        let invalid = boids_system(&mut boids, &[], &[], &Rect::new(0.0, 0.0, 100.0, 100.0), 1.0 / 60.0);
+
+        assert!(invalid.is_empty());
+        assert!(boids.iter().all(|b| b.vel.is_finite()));
+    }
+
+    #[test]
+    fn non_finite_velocity_is_reported_and_stopped() {
+        let config = config();
+        let positions = [vec2(50.0, 50.0), vec2(90.0, 90.0)];
+        let mut velocities = [vec2(f32::NAN, 0.0), vec2(1.0, 0.0)];
+        let mut boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+
+        let invalid = boids_system(&mut boids, &[], &[], &Rect::new(0.0, 0.0, 100.0, 100.0), 1.0 / 60.0);
+
+        assert_eq!(invalid, vec![0]);
+        assert_eq!(*boids[0].vel, vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn non_finite_neighbors_do_not_poison_the_boids_before_them() {
+        let config = config();
+        let positions = [vec2(50.0, 50.0), vec2(60.0, 50.0), vec2(55.0, 55.0), vec2(f32::NAN, 50.0)];
+        let mut velocities = [vec2(1.0, 0.0), vec2(1.0, 0.0), vec2(f32::NAN, 0.0), vec2(1.0, 0.0)];
+        let mut boids: Vec<Boid> = positions.iter().zip(velocities.iter_mut())
+            .map(|(pos, vel)| Boid { id: None, pos, vel, config: &config, energy: None, behavior: None })
+            .collect();
+
+        let invalid = boids_system(&mut boids, &[], &[], &Rect::new(0.0, 0.0, 100.0, 100.0), 1.0 / 60.0);
+
+        // only the boid with the non-finite velocity is at fault, the one at a non-finite position sees no neighbor
+        assert_eq!(invalid, vec![2]);
+        assert!(boids[..2].iter().all(|b| b.vel.is_finite() && *b.vel != vec2(0.0, 0.0)));
+    }
+}
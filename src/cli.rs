@@ -0,0 +1,276 @@
+//! Command line parsing. Every command takes the scene options, then options of its own.
+
+use macroquad::prelude::*;
+
+use crate::evolve::EvolveOptions;
+use crate::export::ExportOptions;
+use crate::headless::RunOptions;
+use crate::render::RenderMode;
+use crate::scene::Scene;
+use crate::share::Share;
+use crate::clock;
+
+const USAGE: &str = "usage: fish-flocking-simulation [COMMAND] [SCENE OPTIONS] [OPTIONS]
+
+commands:
+  view     opens the viewer (the default)
+  run      simulates headless, writing trajectories, metrics or snapshots
+  evolve   tunes the config of a flock with a genetic algorithm
+  export   renders an image sequence at a fixed timestep
+
+run `fish-flocking-simulation COMMAND --help` for the options of a command";
+
+const SCENE_USAGE: &str = "scene options:
+  --scene PATH        JSON scene to use instead of the built-in one
+  --share CODE        seed, counts and configs from a share link of the web build
+  --seed N            seed of the random number generator, random when missing
+  --count NAME=N      number of boids of the archetype NAME, can be repeated
+  --world-size WxH    size of the world, and of the viewer window";
+
+/// Options of a command, given as `--name value` pairs.
+pub trait Options: Default {
+    const USAGE: &'static str;
+
+    /// Applies one option, returns false when it is not one of this command's.
+    fn set(&mut self, arg: &str, value: &str) -> Result<bool, String>;
+
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub fn parse_value<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_error| format!("invalid value for {}: {}", arg, value))
+}
+
+pub enum Command {
+    View(ViewOptions),
+    Run(RunOptions),
+    Evolve(EvolveOptions),
+    Export(ExportOptions),
+    /// Asked for the usage, which is to be printed.
+    Help(String)
+}
+
+pub struct Cli {
+    pub scene: Scene,
+    /// What `scene` was loaded from, to load it again when the file changes.
+    pub scene_options: SceneOptions,
+    pub command: Command
+}
+
+pub fn parse(args: &[String]) -> Result<Cli, String> {
+    let (name, args) = match args.first() {
+        Some(first) if !first.starts_with('-') => (first.as_str(), &args[1..]),
+        _ => ("view", args)
+    };
+    let mut scene = SceneOptions::default();
+    let command = match name {
+        "help" => Command::Help(format!("{}\n\n{}", USAGE, SCENE_USAGE)),
+        "view" => parse_command(args, &mut scene, Command::View)?,
+        "run" => parse_command(args, &mut scene, Command::Run)?,
+        "evolve" => parse_command(args, &mut scene, Command::Evolve)?,
+        "export" => parse_command(args, &mut scene, Command::Export)?,
+        _ => return Err(format!("unknown command {}\n\n{}", name, USAGE))
+    };
+
+    Ok(Cli {
+        scene: scene.load()?,
+        scene_options: scene,
+        command
+    })
+}
+
+fn parse_command<T: Options>(args: &[String], scene: &mut SceneOptions, command: fn(T) -> Command) -> Result<Command, String> {
+    let usage = format!("{}\n\n{}", T::USAGE, SCENE_USAGE);
+    let with_usage = |message: String| format!("{}\n\n{}", message, usage);
+    let mut options = T::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Ok(Command::Help(usage));
+        }
+        let value = args.next().ok_or_else(|| with_usage(format!("missing value for {}", arg)))?;
+        let known = scene.set(arg, value).map_err(with_usage)? || options.set(arg, value).map_err(with_usage)?;
+        if !known {
+            return Err(with_usage(format!("unknown option {}", arg)));
+        }
+    }
+
+    options.validate().map_err(with_usage)?;
+    Ok(command(options))
+}
+
+#[derive(Default)]
+pub struct SceneOptions {
+    pub path: Option<String>,
+    /// Share code applied to the scene before the other overrides.
+    pub share: Option<String>,
+    pub seed: Option<u64>,
+    /// Boid count by archetype name.
+    pub counts: Vec<(String, usize)>,
+    pub world_size: Option<Vec2>
+}
+
+impl Options for SceneOptions {
+    const USAGE: &'static str = SCENE_USAGE;
+
+    fn set(&mut self, arg: &str, value: &str) -> Result<bool, String> {
+        match arg {
+            "--scene" => self.path = Some(value.to_string()),
+            "--share" => self.share = Some(value.to_string()),
+            "--seed" => self.seed = Some(parse_value(arg, value)?),
+            "--count" => {
+                let (name, count) = value.split_once('=').ok_or(format!("--count expects NAME=N, got {}", value))?;
+                self.counts.push((name.to_string(), parse_value(arg, count)?));
+            },
+            "--world-size" => {
+                let size = value.split_once('x')
+                    .and_then(|(w, h)| Some(vec2(w.parse().ok()?, h.parse().ok()?)))
+                    .ok_or(format!("--world-size expects WIDTHxHEIGHT, got {}", value))?;
+                if size.x < 1.0 || size.y < 1.0 {
+                    return Err(format!("--world-size must be positive, got {}", value));
+                }
+                self.world_size = Some(size);
+            },
+            _ => return Ok(false)
+        }
+        Ok(true)
+    }
+}
+
+impl SceneOptions {
+    /// Reads the scene file (or takes the built-in scene) and applies the overrides.
+    pub fn load(&self) -> Result<Scene, String> {
+        let mut scene = match &self.path {
+            Some(path) => {
+                let json = std::fs::read_to_string(path).map_err(|error| format!("could not read the scene {}: {}", path, error))?;
+                serde_json::from_str(&json).map_err(|error| format!("invalid scene {}: {}", path, error))?
+            },
+            None => Scene::default()
+        };
+
+        if let Some(code) = &self.share {
+            Share::decode(code)?.apply(&mut scene).map_err(|error| format!("--share: {}", error))?;
+        }
+        if self.seed.is_some() {
+            scene.seed = self.seed;
+        }
+        if let Some(world_size) = self.world_size {
+            scene.world_size = world_size;
+        }
+        for (name, count) in &self.counts {
+            let names: Vec<String> = scene.archetypes.iter().map(|a| a.name.clone()).collect();
+            let archetype = scene.archetypes.iter_mut()
+                .find(|a| a.name == *name)
+                .ok_or(format!("--count: the scene has no archetype {} (it has {})", name, names.join(", ")))?;
+            archetype.count = *count;
+        }
+
+        validate_scene(&scene)?;
+        Ok(scene)
+    }
+}
+
+/// Rejects values the simulation cannot run with.
+fn validate_scene(scene: &Scene) -> Result<(), String> {
+    for archetype in &scene.archetypes {
+        let Some(animator) = &archetype.animator else {
+            continue;
+        };
+        if !(animator.reference_speed.is_finite() && animator.reference_speed > 0.0) {
+            return Err(format!("the animator of {} needs a positive reference_speed, got {}", archetype.name, animator.reference_speed));
+        }
+    }
+    Ok(())
+}
+
+pub struct ViewOptions {
+    /// Initial speed of the simulation, see `clock::TIME_SCALES`.
+    pub time_scale: f32,
+    /// Ticks between two samples of the trajectories `R` records.
+    pub interval: u64,
+    /// Where `R` records trajectories.
+    pub csv: String,
+    pub columnar: String,
+    /// Where `F5` saves and `F9` loads the simulation.
+    pub snapshot: String,
+    /// How boids are drawn at start, `Tab` switches to the next mode.
+    pub render_mode: RenderMode
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        ViewOptions {
+            time_scale: 1.0,
+            interval: 6,
+            csv: "trajectories.csv".to_string(),
+            columnar: "trajectories.traj".to_string(),
+            snapshot: "snapshot.json".to_string(),
+            render_mode: RenderMode::Sprites
+        }
+    }
+}
+
+impl Options for ViewOptions {
+    const USAGE: &'static str = "usage: fish-flocking-simulation view [SCENE OPTIONS] [--time-scale X] [--interval N] [--csv PATH] [--columnar PATH] [--snapshot PATH] [--render-mode sprites|batched|triangles|points]";
+
+    fn set(&mut self, arg: &str, value: &str) -> Result<bool, String> {
+        match arg {
+            "--time-scale" => self.time_scale = parse_value(arg, value)?,
+            "--interval" => self.interval = parse_value(arg, value)?,
+            "--csv" => self.csv = value.to_string(),
+            "--columnar" => self.columnar = value.to_string(),
+            "--snapshot" => self.snapshot = value.to_string(),
+            "--render-mode" => self.render_mode = parse_value(arg, value)?,
+            _ => return Ok(false)
+        }
+        Ok(true)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.interval == 0 {
+            return Err("--interval must be positive".to_string());
+        }
+        clock::validate_scale(self.time_scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn scene_options_override_the_scene() {
+        let cli = parse(&args("run --seed 7 --count shark=2 --world-size 640x480 --ticks 10 --save out.json")).unwrap();
+        assert_eq!(cli.scene.seed, Some(7));
+        assert_eq!(cli.scene.world_size, vec2(640.0, 480.0));
+        assert_eq!(cli.scene.archetypes.iter().find(|a| a.name == "shark").unwrap().count, 2);
+        assert!(matches!(cli.command, Command::Run(RunOptions { ticks: 10, .. })));
+        assert!(matches!(parse(&[]).unwrap().command, Command::View(_)));
+        assert!(matches!(parse(&args("--render-mode points")).unwrap().command, Command::View(ViewOptions { render_mode: RenderMode::Points, .. })));
+        assert!(matches!(parse(&args("view --interval 2")).unwrap().command, Command::View(ViewOptions { interval: 2, .. })));
+    }
+
+    #[test]
+    fn invalid_options_are_reported() {
+        let error = |line: &str| parse(&args(line)).err().unwrap_or_default();
+        assert!(error("run --count whale=3 --save out.json").contains("no archetype whale"));
+        assert!(error("--time-scale 50").contains("--time-scale"));
+        assert!(error("--interval 0").contains("--interval"));
+        assert!(error("--world-size 100").contains("WIDTHxHEIGHT"));
+        assert!(error("--render-mode voxels").starts_with("invalid value for --render-mode"));
+        assert!(error("evolve --bogus 1").starts_with("unknown option --bogus"));
+        assert!(error("fly").starts_with("unknown command fly"));
+        assert!(matches!(parse(&args("export --help")).unwrap().command, Command::Help(_)));
+
+        let mut scene = Scene::default();
+        assert!(validate_scene(&scene).is_ok());
+        scene.archetypes[0].animator.as_mut().unwrap().reference_speed = 0.0;
+        assert!(validate_scene(&scene).unwrap_err().contains("reference_speed"));
+    }
+}
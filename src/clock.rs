@@ -0,0 +1,116 @@
+use macroquad::prelude::*;
+
+/// Duration of every tick the viewer simulates, and the longest tick the simulation is advanced by at once.
+pub const FIXED_TICK: f32 = 1.0 / 60.0;
+
+pub const TIME_SCALES: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 10.0];
+
+// frame times above this (window dragged, debugger break) are clamped so the world does not jump
+const MAX_FRAME_TIME: f32 = 0.25;
+
+// time a hair short of a tick still makes one, so frames of exactly a tick do not alternate between 0 and 2 ticks
+const TICK_TOLERANCE: f32 = FIXED_TICK * 1e-3;
+
+/// Turns real frame times into simulation ticks.
+pub struct Clock {
+    pub paused: bool,
+    scale: f32,
+    /// Scaled time not simulated yet, less than a tick.
+    accumulated: f32
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::with_scale(1.0)
+    }
+}
+
+/// Checks a time scale is within `TIME_SCALES`.
+pub fn validate_scale(scale: f32) -> Result<(), String> {
+    let (min, max) = (TIME_SCALES[0], TIME_SCALES[TIME_SCALES.len() - 1]);
+    if (min..=max).contains(&scale) {
+        Ok(())
+    } else {
+        Err(format!("--time-scale must be between {} and {}, got {}", min, max, scale))
+    }
+}
+
+impl Clock {
+    pub fn with_scale(scale: f32) -> Clock {
+        Clock {
+            paused: false,
+            scale,
+            accumulated: 0.0
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Switches to the next of `TIME_SCALES` above the current scale.
+    pub fn faster(&mut self) {
+        self.scale = TIME_SCALES.into_iter().find(|s| *s > self.scale).unwrap_or(self.scale);
+    }
+
+    pub fn slower(&mut self) {
+        self.scale = TIME_SCALES.into_iter().rev().find(|s| *s < self.scale).unwrap_or(self.scale);
+    }
+
+    /// Durations of the ticks to simulate for a frame that took `frame_time`. Every tick lasts `FIXED_TICK`
+    /// and the scaled time left over is carried to the next frame, so a run with the same seed and
+    /// inputs goes the same way whatever the frame rate, and `boids_system` stays stable at high speeds.
+    pub fn ticks(&mut self, frame_time: f32) -> Vec<f32> {
+        if self.paused {
+            return vec![];
+        }
+
+        self.accumulated += frame_time.min(MAX_FRAME_TIME) * self.scale();
+        let count = ((self.accumulated + TICK_TOLERANCE) / FIXED_TICK).floor().max(0.0);
+        self.accumulated -= count * FIXED_TICK;
+        vec![FIXED_TICK; count as usize]
+    }
+}
+
+/// Splits simulated time in equal ticks no longer than `FIXED_TICK`, however long it is.
+pub fn split(duration: f32) -> Vec<f32> {
+    let count = (duration / FIXED_TICK).ceil().max(1.0) as usize;
+    vec![duration / count as f32; count]
+}
+
+pub fn clock_hud_system(clock: &Clock) {
+    let text = if clock.paused {
+        "paused".to_string()
+    } else {
+        format!("{}x", clock.scale())
+    };
+    let size = measure_text(&text, None, 18, 1.0);
+    draw_text(&text, screen_width() - size.width - 10.0, 22.0, 18.0, WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_time_is_split_in_short_ticks() {
+        let mut clock = Clock::default();
+        assert!(clock.ticks(FIXED_TICK / 2.0).is_empty());
+        assert_eq!(clock.ticks(FIXED_TICK / 2.0), vec![FIXED_TICK]);
+        assert_eq!(clock.ticks(FIXED_TICK), vec![FIXED_TICK]);
+
+        for _ignored in 0..TIME_SCALES.len() {
+            clock.faster();
+        }
+        assert_eq!(clock.scale(), 10.0);
+        let ticks = clock.ticks(FIXED_TICK);
+        assert_eq!(ticks.len(), 10);
+        assert!(ticks.iter().all(|t| *t == FIXED_TICK));
+
+        clock.paused = true;
+        assert!(clock.ticks(FIXED_TICK).is_empty());
+
+        // frames of offline exports are not clamped like real frame times
+        assert_eq!(split(1.0).len(), 60);
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use crate::boid;
+use crate::movement::Movement;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Energy {
+    pub current: f32,
+    pub max: f32,
+    /// Energy spent per second just to stay alive.
+    pub base_drain: f32,
+    /// Extra energy spent per second when swimming at `max_speed`, growing with the square of the speed.
+    pub speed_drain: f32,
+    /// Energy regained per second while swimming no faster than `rest_speed`.
+    pub rest_recovery: f32,
+    pub rest_speed: f32,
+    /// Share of the speed and rule weights left when the energy runs out.
+    pub exhausted_vigor: f32,
+    /// Below this share of `max` the boid starts hunting.
+    pub hungry_below: f32
+}
+
+impl Energy {
+    pub fn fraction(&self) -> f32 {
+        if self.max > 0.0 {
+            (self.current / self.max).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Scales the boid's speed and rule weights, from `exhausted_vigor` when empty to `1.0` when full.
+    pub fn vigor(&self) -> f32 {
+        self.exhausted_vigor + (1.0 - self.exhausted_vigor) * self.fraction()
+    }
+
+    pub fn is_hungry(&self) -> bool {
+        self.fraction() < self.hungry_below
+    }
+
+    pub fn restore(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+}
+
+pub fn energy_system(input: &mut Vec<(&mut Energy, &Movement, &boid::Config)>, elapsed: f32) {
+    for (energy, movement, config) in input {
+        let speed = movement.vel.length();
+        let relative_speed = if config.max_speed > 0.0 { speed / config.max_speed } else { 0.0 };
+        let mut change = -energy.base_drain - energy.speed_drain * relative_speed * relative_speed;
+        if speed <= energy.rest_speed {
+            change += energy.rest_recovery;
+        }
+        energy.current = (energy.current + change * elapsed).clamp(0.0, energy.max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::*;
+
+    use super::*;
+    use crate::scene::Scene;
+
+    fn energy() -> Energy {
+        Energy {
+            current: 50.0,
+            max: 100.0,
+            base_drain: 1.0,
+            speed_drain: 4.0,
+            rest_recovery: 3.0,
+            rest_speed: 20.0,
+            exhausted_vigor: 0.4,
+            hungry_below: 0.5
+        }
+    }
+
+    #[test]
+    fn swimming_drains_and_resting_recovers() {
+        let config = Scene::default().archetypes[0].config.clone();
+        let (mut fast, mut resting, mut full) = (energy(), energy(), Energy { current: 100.0, ..energy() });
+        let full_speed = Movement { vel: vec2(config.max_speed, 0.0) };
+        let rest = Movement { vel: vec2(0.0, 0.0) };
+        energy_system(&mut vec![(&mut fast, &full_speed, &config), (&mut resting, &rest, &config), (&mut full, &rest, &config)], 2.0);
+
+        // base drain plus the whole speed drain at top speed, the recovery outweighs the base drain at rest
+        assert_eq!(fast.current, 50.0 - 2.0 * (1.0 + 4.0));
+        assert_eq!(resting.current, 50.0 + 2.0 * (3.0 - 1.0));
+        assert_eq!(full.current, 100.0);
+    }
+
+    #[test]
+    fn vigor_and_hunger_follow_the_energy_left() {
+        let mut energy = energy();
+        assert!(!energy.is_hungry());
+        assert_eq!(energy.vigor(), 0.4 + 0.6 * 0.5);
+
+        energy.current = 0.0;
+        assert!(energy.is_hungry());
+        assert_eq!(energy.vigor(), 0.4);
+
+        energy.restore(500.0);
+        assert_eq!((energy.current, energy.vigor()), (100.0, 1.0));
+        assert_eq!(Energy { max: 0.0, ..energy }.fraction(), 0.0);
+    }
+}
@@ -1,33 +1,58 @@
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use macroquad::math::*;
 use macroquad::prelude::*;
 
+use crate::animator::{Animated, Animator, AnimatorState};
+use crate::assets;
 use crate::boid;
 use crate::boid::Boid;
+use crate::energy::Energy;
+use crate::food;
+use crate::food::{Feeder, Food};
+use crate::life::{Life, Reproduction};
 use crate::movement::{Movement, Transform2D};
-use crate::render::{Animation, AnimationDefinition, RenderableTexture, TextureAtlas};
+use crate::obstacle::Obstacle;
+use crate::predation::Predator;
+use crate::rng;
+use crate::render::{Animation, AnimationClip, AnimationDefinition, AnimationFrame, PlayMode, RenderableTexture, Sprite, TextureAtlas};
+use crate::scene::{Archetype, Scene};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Entity {
+    /// Stable identifier handed out by the world, `None` until the entity has been added to one.
+    pub id: Option<u64>,
     pub transform: Option<Transform2D>,
     pub movement: Option<Movement>,
     pub config: Option<boid::Config>,
+    pub behavior: Option<boid::Behavior>,
+    /// Name of the archetype the entity was spawned from, so it can be updated when the scene changes.
+    pub archetype: Option<String>,
+    /// Key of the sprite the animation and texture come from, so they can be restored from a snapshot.
+    pub sprite: Option<String>,
     pub animation: Option<Animation>,
-    pub renderable_texture: Option<RenderableTexture>
+    pub animator: Option<Animator>,
+    pub animator_state: Option<AnimatorState>,
+    pub renderable_texture: Option<RenderableTexture>,
+    pub obstacle: Option<Obstacle>,
+    pub energy: Option<Energy>,
+    pub predator: Option<Predator>,
+    pub food: Option<Food>,
+    pub feeder: Option<Feeder>,
+    pub life: Option<Life>,
+    pub reproduction: Option<Reproduction>
 }
 
-pub fn query_mut<'a, T: TryFrom<&'a mut Entity>>(entities: &'a mut Vec<Entity>) -> Vec<T> {
+pub fn query_mut<'a, T: TryFrom<&'a mut Entity>>(entities: &'a mut [Entity]) -> Vec<T> {
     entities.iter_mut()
-        .map(|e| T::try_from(e))
-        .flatten()
+        .flat_map(|e| T::try_from(e))
         .collect()
 }
 
-pub fn query<'a, T: TryFrom<&'a Entity>>(entities: &'a mut Vec<Entity>) -> Vec<T> {
+pub fn query<'a, T: TryFrom<&'a Entity>>(entities: &'a [Entity]) -> Vec<T> {
     entities.iter()
-        .map(|e| T::try_from(e))
-        .flatten()
+        .flat_map(|e| T::try_from(e))
         .collect()
 }
 
@@ -37,9 +62,12 @@ impl <'a> TryFrom<&'a mut Entity> for Boid<'a> {
     fn try_from(value: &'a mut Entity) -> Result<Self, Self::Error> {
         match (&value.transform, &mut value.movement, &value.config) {
             (Some(transform), Some(movement), Some(config)) => Ok(Boid {
+                id: value.id,
                 pos: &transform.pos,
                 vel: &mut movement.vel,
-                config
+                config,
+                energy: value.energy.as_ref(),
+                behavior: value.behavior.as_mut()
             }),
             _ => Err(())
         }
@@ -68,6 +96,47 @@ impl <'a> TryFrom<&'a Entity> for (&'a Transform2D, &'a RenderableTexture) {
     }
 }
 
+impl <'a> TryFrom<&'a Entity> for (&'a Transform2D, &'a Obstacle) {
+    type Error = ();
+
+    fn try_from(value: &'a Entity) -> Result<Self, Self::Error> {
+        match (&value.transform, &value.obstacle) {
+            (Some(a), Some(b)) => Ok((a, b)),
+            _ => Err(())
+        }
+    }
+}
+
+impl <'a> TryFrom<&'a Entity> for (&'a Transform2D, &'a Food) {
+    type Error = ();
+
+    fn try_from(value: &'a Entity) -> Result<Self, Self::Error> {
+        match (&value.transform, &value.food) {
+            (Some(a), Some(b)) => Ok((a, b)),
+            _ => Err(())
+        }
+    }
+}
+
+impl <'a> TryFrom<&'a mut Entity> for &'a mut Food {
+    type Error = ();
+
+    fn try_from(value: &'a mut Entity) -> Result<Self, Self::Error> {
+        value.food.as_mut().ok_or(())
+    }
+}
+
+impl <'a> TryFrom<&'a Entity> for (&'a Transform2D, &'a Movement, &'a boid::Config) {
+    type Error = ();
+
+    fn try_from(value: &'a Entity) -> Result<Self, Self::Error> {
+        match (&value.transform, &value.movement, &value.config) {
+            (Some(a), Some(b), Some(c)) => Ok((a, b, c)),
+            _ => Err(())
+        }
+    }
+}
+
 impl <'a> TryFrom<&'a mut Entity> for (&'a mut Transform2D, &'a Movement) {
     type Error = ();
 
@@ -79,114 +148,207 @@ impl <'a> TryFrom<&'a mut Entity> for (&'a mut Transform2D, &'a Movement) {
     }
 }
 
-impl <'a> TryFrom<&'a mut Entity> for (&'a mut Transform2D, &'a mut Animation, &'a Movement) {
+impl <'a> TryFrom<&'a mut Entity> for (&'a mut Energy, &'a Movement, &'a boid::Config) {
     type Error = ();
 
     fn try_from(value: &'a mut Entity) -> Result<Self, Self::Error> {
-        match (&mut value.transform, &mut value.animation, &value.movement) {
+        match (&mut value.energy, &value.movement, &value.config) {
             (Some(a), Some(b), Some(c)) => Ok((a, b, c)),
             _ => Err(())
         }
     }
 }
 
-pub async fn setup_entities<'a>() -> Vec<Entity> {
-    let window_width = screen_width();
-    let window_height = screen_height();
-    let bounds_margin = 120.0;
-    let boid_config = boid::Config {
-        bounds_margin,
-        neighbor_distance: 200.0,
-        separation_distance: 20.0,
-        avoidance_distance: 80.0,
-        separation_rule_weight: 0.75,
-        avoidance_rule_weight: 0.2,
-        cohesion_rule_weight: 0.01,
-        alignment_rule_weight: 0.03,
-        bounds_rule_weight: 0.02,
-        exploration_rule_weight: 0.04,
-        field_of_view: -0.5,
-        max_speed: 140.0,
-        flock_id: 1,
-        flock_to_avoid: Default::default()
-    };
+impl <'a> TryFrom<&'a mut Entity> for Animated<'a> {
+    type Error = ();
 
-    let texture_atlas_fish = Rc::new(TextureAtlas {
-        texture: load_texture("resources/fish_spritesheet.png").await.unwrap(),
-        num_tiles: vec2(12.0, 8.0)
-    });
+    fn try_from(value: &'a mut Entity) -> Result<Self, Self::Error> {
+        match (&mut value.animation, &value.movement) {
+            (Some(animation), Some(movement)) => Ok(Animated {
+                animation,
+                animator: value.animator.as_ref(),
+                state: value.animator_state.as_mut(),
+                movement,
+                behavior: value.behavior.unwrap_or_default()
+            }),
+            _ => Err(())
+        }
+    }
+}
 
-    let fish_animation_definition_0 = Rc::new(AnimationDefinition {
-        atlas: texture_atlas_fish.clone(),
-        duration_per_frame: 0.2,
-        frame_coords: vec![vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)]
-    });
+pub fn spawn_archetype(archetype: &Archetype, sprites: &HashMap<String, Sprite>, pos: Vec2, vel: Vec2) -> Entity {
+    let sprite = sprites.get(&archetype.sprite);
+    Entity {
+        transform: Some(Transform2D {
+            pos,
+            rot_radians: 0.0
+        }),
+        movement: Some(Movement {
+            vel
+        }),
+        config: Some(archetype.config.clone()),
+        behavior: Some(boid::Behavior::Idle),
+        archetype: Some(archetype.name.clone()),
+        sprite: Some(archetype.sprite.clone()),
+        animation: sprite.map(|s| s.animation.spawned()),
+        animator: archetype.animator.clone(),
+        animator_state: archetype.animator.as_ref().map(|_animator| AnimatorState::default()),
+        renderable_texture: sprite.map(|s| s.renderable_texture.clone()),
+        energy: archetype.energy.clone(),
+        predator: archetype.predator.clone(),
+        life: archetype.life.clone(),
+        reproduction: archetype.reproduction.clone(),
+        ..Default::default()
+    }
+}
 
-    let fish_animation_definition_1 = Rc::new(AnimationDefinition {
-        frame_coords: vec![vec2(6.0, 4.0), vec2(7.0, 4.0), vec2(8.0, 4.0)],
-        atlas: texture_atlas_fish.clone(),
-        ..*fish_animation_definition_0
-    });
+/// Spawns the scene's entities. Archetypes whose sprite is missing from `sprites` are spawned
+/// without visuals, which is how headless runs skip loading textures.
+pub fn spawn_scene(scene: &Scene, sprites: &HashMap<String, Sprite>) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = scene.archetypes.iter()
+        .flat_map(|archetype| spawn_boids(archetype, sprites, archetype.count, scene.world_size))
+        .collect();
 
-    let fish_animation_definition_2 = Rc::new(AnimationDefinition {
-        frame_coords: vec![vec2(6.0, 0.0), vec2(7.0, 0.0), vec2(8.0, 0.0)],
-        atlas: texture_atlas_fish.clone(),
-        ..*fish_animation_definition_0
-    });
+    // spread the ages so the initial population does not die out all at once
+    for life in entities.iter_mut().filter_map(|e| e.life.as_mut()) {
+        life.age = rng::gen_range(0.0, life.lifespan / 2.0);
+    }
+
+    entities.extend(spawn_food(scene));
+    entities.extend(spawn_feeders(scene));
+    entities.extend(spawn_obstacles(scene));
+    entities
+}
+
+/// Spawns `count` boids of the archetype at random positions in the world.
+pub fn spawn_boids(archetype: &Archetype, sprites: &HashMap<String, Sprite>, count: usize, world_size: Vec2) -> Vec<Entity> {
+    let (width, height) = (world_size.x, world_size.y);
+    (0..count)
+        .map(|_| {
+            let pos = vec2(rng::gen_range(0.0, width), rng::gen_range(0.0, height));
+            let vel = vec2(rng::gen_range(-width, width), rng::gen_range(-height, height));
+            spawn_archetype(archetype, sprites, pos, vel)
+        })
+        .collect()
+}
 
-    let fish_animation_definition_3 = Rc::new(AnimationDefinition {
-        frame_coords: vec![vec2(9.0, 0.0), vec2(10.0, 0.0), vec2(11.0, 0.0)],
-        atlas: texture_atlas_fish.clone(),
-        ..*fish_animation_definition_0
+pub fn spawn_food(scene: &Scene) -> Vec<Entity> {
+    let (width, height) = (scene.world_size.x, scene.world_size.y);
+    scene.food.iter()
+        .flat_map(|spawn| (0..spawn.count).map(|_| {
+            let pos = vec2(rng::gen_range(0.0, width), rng::gen_range(0.0, height));
+            food::food_entity(pos, spawn.food.clone())
+        }))
+        .collect()
+}
+
+pub fn spawn_feeders(scene: &Scene) -> Vec<Entity> {
+    scene.feeders.iter()
+        .map(|spawn| Entity {
+            transform: Some(Transform2D {
+                pos: spawn.pos,
+                rot_radians: 0.0
+            }),
+            feeder: Some(spawn.feeder.clone()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+pub fn spawn_obstacles(scene: &Scene) -> Vec<Entity> {
+    scene.obstacles.iter()
+        .map(|spawn| Entity {
+            transform: Some(Transform2D {
+                pos: spawn.pos,
+                rot_radians: 0.0
+            }),
+            obstacle: Some(spawn.obstacle.clone()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// The sprites with the clips of `clips` instead of their built-in ones, for the sprites it names.
+pub fn with_clips(sprites: &HashMap<String, Sprite>, clips: &HashMap<String, Vec<AnimationClip>>) -> HashMap<String, Sprite> {
+    let mut sprites = sprites.clone();
+    for (key, clips) in clips {
+        if let Some(sprite) = sprites.get_mut(key) {
+            let definition = &sprite.animation.definition;
+            sprite.animation = Animation::new(Rc::new(AnimationDefinition { clips: clips.clone(), ..(**definition).clone() }));
+        }
+    }
+    sprites
+}
+
+pub async fn load_sprites() -> HashMap<String, Sprite> {
+    let texture_atlas_fish = Rc::new(TextureAtlas {
+        texture: assets::load_spritesheet(assets::FISH_SPRITESHEET, vec2(12.0, 8.0), ORANGE).await,
+        num_tiles: vec2(12.0, 8.0)
     });
 
+    // the sheet has the frames of each fish in a row of 3 tiles, from `first`
+    let fish_animation_definition = |first: Vec2| {
+        let frames = [first, first + vec2(1.0, 0.0), first + vec2(2.0, 0.0)];
+        Rc::new(AnimationDefinition {
+            atlas: texture_atlas_fish.clone(),
+            clips: vec![
+                AnimationClip::uniform("swim", &frames, 0.2, PlayMode::Loop),
+                AnimationClip {
+                    next: Some("swim".to_string()),
+                    ..AnimationClip::uniform("turn", &frames[1..2], 0.15, PlayMode::Once)
+                },
+                AnimationClip::uniform("dash", &frames, 0.08, PlayMode::Loop),
+                AnimationClip {
+                    name: "eaten".to_string(),
+                    frames: vec![
+                        AnimationFrame { coord: frames[2], duration: 0.05 },
+                        AnimationFrame { coord: frames[1], duration: 0.05 },
+                        AnimationFrame { coord: frames[0], duration: 0.3 }
+                    ],
+                    mode: PlayMode::Once,
+                    next: None
+                }
+            ],
+            random_phase: true
+        })
+    };
+    let fish_animation_definition_0 = fish_animation_definition(vec2(0.0, 0.0));
+    let fish_animation_definition_1 = fish_animation_definition(vec2(6.0, 4.0));
+    let fish_animation_definition_2 = fish_animation_definition(vec2(6.0, 0.0));
+    let fish_animation_definition_3 = fish_animation_definition(vec2(9.0, 0.0));
+
     let texture_atlas_shark = Rc::new(TextureAtlas {
-        texture: load_texture("resources/shark_spritesheet.png").await.unwrap(),
+        texture: assets::load_spritesheet(assets::SHARK_SPRITESHEET, vec2(3.0, 4.0), LIGHTGRAY).await,
         num_tiles: vec2(3.0, 4.0)
     });
 
+    let shark_frames = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)];
     let shark_animation_definition_0 = Rc::new(AnimationDefinition {
         atlas: texture_atlas_shark.clone(),
-        duration_per_frame: 0.2,
-        frame_coords: vec![vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)]
+        clips: vec![
+            AnimationClip::uniform("swim", &shark_frames, 0.2, PlayMode::Loop),
+            AnimationClip::uniform("dash", &shark_frames, 0.1, PlayMode::Loop),
+            // jaws open wide for a moment, played by `predation_system` on every catch
+            AnimationClip {
+                name: "bite".to_string(),
+                frames: vec![
+                    AnimationFrame { coord: shark_frames[1], duration: 0.05 },
+                    AnimationFrame { coord: shark_frames[2], duration: 0.25 },
+                    AnimationFrame { coord: shark_frames[1], duration: 0.05 }
+                ],
+                mode: PlayMode::Once,
+                next: Some("swim".to_string())
+            }
+        ],
+        random_phase: true
     });
 
-    let shark_flock_id = 10;
-
-    let shark_archetype_0 = Entity {
-        transform: Some(Transform2D {
-            pos: vec2(0.0, 0.0),
-            rot_radians: 0.0
-        }),
-        movement: Some(Movement {
-            vel: vec2(0.0, 0.0)
-        }),
-        config: Some(boid::Config {
-            bounds_margin,
-            flock_id: shark_flock_id,
-            neighbor_distance: 200.0,
-            separation_distance: 100.0,
-            avoidance_distance: 100.0,
-            separation_rule_weight: 0.0,
-            avoidance_rule_weight: 0.0,
-            cohesion_rule_weight: 0.0,
-            alignment_rule_weight: 0.000,
-            bounds_rule_weight: 0.003,
-            exploration_rule_weight: 0.0015,
-            field_of_view: -0.9,
-            max_speed: 50.0,
-            flock_to_avoid: Default::default()
-        }),
-        animation: Some(Animation {
-            definition: shark_animation_definition_0.clone(),
-            tick: 0.0,
-            speed: 1.0,
-            frame_number: 0
-        }),
-        renderable_texture: Some(RenderableTexture {
+    let shark_sprite = Sprite {
+        animation: Animation::new(shark_animation_definition_0.clone()),
+        renderable_texture: RenderableTexture {
             texture: texture_atlas_shark.texture.weak_clone(),
             pos_offset: vec2(-40.0, -50.0),
-            rot_offset_radians: -(6.28319 / 4.0),
+            rot_offset_radians: -(std::f32::consts::TAU / 4.0),
             color: WHITE,
             params: DrawTextureParams {
                 dest_size: Some(vec2(80.0, 80.0)),
@@ -196,101 +358,15 @@ pub async fn setup_entities<'a>() -> Vec<Entity> {
                 flip_y: false,
                 pivot: None
             }
-        })
-    };
-
-    let fish_archetype_0 = Entity {
-        transform: Some(Transform2D {
-            pos: vec2(0.0, 0.0),
-            rot_radians: 0.0
-        }),
-        movement: Some(Movement {
-            vel: vec2(0.0, 0.0)
-        }),
-        config: Some(boid::Config {
-            flock_to_avoid: vec![shark_flock_id].into_iter().collect(),
-            ..boid_config.clone()
-        }),
-        animation: Some(Animation {
-            definition: fish_animation_definition_0.clone(),
-            tick: 0.0,
-            speed: 1.0,
-            frame_number: 0
-        }),
-        renderable_texture: Some(RenderableTexture {
-            texture: texture_atlas_fish.texture.weak_clone(),
-            pos_offset: vec2(-20.0, -28.0),
-            rot_offset_radians: -(6.28319 / 4.0),
-            color: WHITE,
-            params: DrawTextureParams {
-                dest_size: Some(vec2(40.0, 40.0)),
-                source: None,
-                rotation: 0.0,
-                flip_x: false,
-                flip_y: false,
-                pivot: None
-            }
-        })
-    };
-
-    let fish_archetype_1 = Entity {
-        transform: Some(Transform2D {
-            pos: vec2(0.0, 0.0),
-            rot_radians: 0.0
-        }),
-        movement: Some(Movement {
-            vel: vec2(0.0, 0.0)
-        }),
-        config: Some(boid::Config {
-            // flock_id: 1,
-            flock_to_avoid: vec![shark_flock_id].into_iter().collect(),
-            ..boid_config.clone()
-        }),
-        animation: Some(Animation {
-            definition: fish_animation_definition_1.clone(),
-            tick: 0.0,
-            speed: 1.0,
-            frame_number: 0
-        }),
-        renderable_texture: Some(RenderableTexture {
-            texture: texture_atlas_fish.texture.weak_clone(),
-            pos_offset: vec2(-20.0, -28.0),
-            rot_offset_radians: -(6.28319 / 4.0),
-            color: WHITE,
-            params: DrawTextureParams {
-                dest_size: Some(vec2(40.0, 40.0)),
-                source: None,
-                rotation: 0.0,
-                flip_x: false,
-                flip_y: false,
-                pivot: None
-            }
-        })
+        }
     };
 
-    let fish_archetype_2 = Entity {
-        transform: Some(Transform2D {
-            pos: vec2(0.0, 0.0),
-            rot_radians: 0.0
-        }),
-        movement: Some(Movement {
-            vel: vec2(0.0, 0.0)
-        }),
-        config: Some(boid::Config {
-            // flock_id: 2,
-            flock_to_avoid: vec![shark_flock_id].into_iter().collect(),
-            ..boid_config.clone()
-        }),
-        animation: Some(Animation {
-            definition: fish_animation_definition_2.clone(),
-            tick: 0.0,
-            speed: 1.0,
-            frame_number: 0
-        }),
-        renderable_texture: Some(RenderableTexture {
+    let fish_sprite_0 = Sprite {
+        animation: Animation::new(fish_animation_definition_0.clone()),
+        renderable_texture: RenderableTexture {
             texture: texture_atlas_fish.texture.weak_clone(),
             pos_offset: vec2(-20.0, -28.0),
-            rot_offset_radians: -(6.28319 / 4.0),
+            rot_offset_radians: -(std::f32::consts::TAU / 4.0),
             color: WHITE,
             params: DrawTextureParams {
                 dest_size: Some(vec2(40.0, 40.0)),
@@ -300,100 +376,19 @@ pub async fn setup_entities<'a>() -> Vec<Entity> {
                 flip_y: false,
                 pivot: None
             }
-        })
+        }
     };
 
-    let fish_archetype_3 = Entity {
-        transform: Some(Transform2D {
-            pos: vec2(0.0, 0.0),
-            rot_radians: 0.0
-        }),
-        movement: Some(Movement {
-            vel: vec2(0.0, 0.0)
-        }),
-        config: Some(boid::Config {
-            // flock_id: 3,
-            flock_to_avoid: vec![shark_flock_id].into_iter().collect(),
-            ..boid_config.clone()
-        }),
-        animation: Some(Animation {
-            definition: fish_animation_definition_3.clone(),
-            tick: 0.0,
-            speed: 1.0,
-            frame_number: 0
-        }),
-        renderable_texture: Some(RenderableTexture {
-            texture: texture_atlas_fish.texture.weak_clone(),
-            pos_offset: vec2(-20.0, -28.0),
-            rot_offset_radians: -(6.28319 / 4.0),
-            color: WHITE,
-            params: DrawTextureParams {
-                dest_size: Some(vec2(40.0, 40.0)),
-                source: None,
-                rotation: 0.0,
-                flip_x: false,
-                flip_y: false,
-                pivot: None
-            }
-        })
+    let fish_sprite = |definition: &Rc<AnimationDefinition>| Sprite {
+        animation: Animation::new(definition.clone()),
+        ..fish_sprite_0.clone()
     };
 
-    let mut entities = vec![];
-    for _ignored in 0..30 {
-        entities.push(Entity {
-            transform: Some(Transform2D {
-                pos: vec2(rand::gen_range(0.0, window_width), rand::gen_range(0.0, window_height)),
-                rot_radians: 0.0
-            }),
-            movement: Some(Movement {
-                vel: vec2(rand::gen_range(-window_width, window_width), rand::gen_range(-window_height, window_height))
-            }),
-            ..fish_archetype_0.clone()
-        });
-        entities.push(Entity {
-            transform: Some(Transform2D {
-                pos: vec2(rand::gen_range(0.0, window_width), rand::gen_range(0.0, window_height)),
-                rot_radians: 0.0
-            }),
-            movement: Some(Movement {
-                vel: vec2(rand::gen_range(-window_width, window_width), rand::gen_range(-window_height, window_height))
-            }),
-            ..fish_archetype_1.clone()
-        });
-        entities.push(Entity {
-            transform: Some(Transform2D {
-                pos: vec2(rand::gen_range(0.0, window_width), rand::gen_range(0.0, window_height)),
-                rot_radians: 0.0
-            }),
-            movement: Some(Movement {
-                vel: vec2(rand::gen_range(-window_width, window_width), rand::gen_range(-window_height, window_height))
-            }),
-            ..fish_archetype_2.clone()
-        });
-        entities.push(Entity {
-            transform: Some(Transform2D {
-                pos: vec2(rand::gen_range(0.0, window_width), rand::gen_range(0.0, window_height)),
-                rot_radians: 0.0
-            }),
-            movement: Some(Movement {
-                vel: vec2(rand::gen_range(-window_width, window_width), rand::gen_range(-window_height, window_height))
-            }),
-            ..fish_archetype_3.clone()
-        });
-    }
-
-    for _ignored in 0..6 {
-        entities.push(Entity {
-            transform: Some(Transform2D {
-                pos: vec2(rand::gen_range(0.0, window_width), rand::gen_range(0.0, window_height)),
-                rot_radians: 0.0
-            }),
-            movement: Some(Movement {
-                vel: vec2(rand::gen_range(-window_width, window_width), rand::gen_range(-window_height, window_height))
-            }),
-            ..shark_archetype_0.clone()
-        })
-    }
-
-    entities
-}
\ No newline at end of file
+    vec![
+        ("fish_1".to_string(), fish_sprite(&fish_animation_definition_1)),
+        ("fish_2".to_string(), fish_sprite(&fish_animation_definition_2)),
+        ("fish_3".to_string(), fish_sprite(&fish_animation_definition_3)),
+        ("fish_0".to_string(), fish_sprite_0),
+        ("shark".to_string(), shark_sprite)
+    ].into_iter().collect()
+}
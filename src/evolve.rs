@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+use macroquad::prelude::*;
+use serde::Serialize;
+
+use crate::boid;
+use crate::cli::{parse_value, Options};
+use crate::scene::Scene;
+use crate::{entities, metrics, rng};
+use crate::world::World;
+
+// how often (in ticks) the flock cohesion is sampled during an evaluation
+const SAMPLE_INTERVAL: usize = 30;
+
+// best candidates copied unchanged into the next generation
+const ELITES: usize = 2;
+
+const TOURNAMENT_SIZE: usize = 3;
+
+pub struct EvolveOptions {
+    /// The flock whose config evolves. Every archetype of this flock gets the candidate config.
+    pub flock_id: u8,
+    pub population: usize,
+    pub generations: usize,
+    /// Simulated ticks per evaluation.
+    pub ticks: usize,
+    pub tick_duration: f32,
+    /// Passed to `boid::Config::mutated` when breeding candidates.
+    pub mutation: f32,
+    /// Where the best config is written, besides being printed.
+    pub output: Option<String>
+}
+
+impl Default for EvolveOptions {
+    fn default() -> Self {
+        EvolveOptions {
+            flock_id: 1,
+            population: 12,
+            generations: 8,
+            ticks: 1800,
+            tick_duration: 1.0 / 60.0,
+            mutation: 0.2,
+            output: None
+        }
+    }
+}
+
+impl Options for EvolveOptions {
+    const USAGE: &'static str = "usage: fish-flocking-simulation evolve [SCENE OPTIONS] [--flock ID] [--population N] [--generations N] [--ticks N] [--mutation AMOUNT] [--output PATH]";
+
+    fn set(&mut self, arg: &str, value: &str) -> Result<bool, String> {
+        match arg {
+            "--flock" => self.flock_id = parse_value(arg, value)?,
+            "--population" => self.population = parse_value(arg, value)?,
+            "--generations" => self.generations = parse_value(arg, value)?,
+            "--ticks" => self.ticks = parse_value(arg, value)?,
+            "--mutation" => self.mutation = parse_value(arg, value)?,
+            "--output" => self.output = Some(value.to_string()),
+            _ => return Ok(false)
+        }
+        Ok(true)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.population < 2 {
+            return Err("--population must be at least 2".to_string());
+        }
+        if self.generations == 0 || self.ticks == 0 {
+            return Err("--generations and --ticks must be positive".to_string());
+        }
+        if !(0.0..1.0).contains(&self.mutation) {
+            return Err("--mutation must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How well a config did in one evaluation. Every part is between 0 and 1.
+#[derive(Clone, Copy)]
+pub struct Fitness {
+    /// Share of the flock it started with still alive at the end, offspring do not count.
+    pub survival: f32,
+    /// How close the flock members keep to their nearest neighbor, relative to the cohesion perception.
+    pub cohesion: f32,
+    /// Average energy of the flock at the end.
+    pub efficiency: f32
+}
+
+impl Fitness {
+    pub fn score(&self) -> f32 {
+        (self.survival + self.cohesion + self.efficiency) / 3.0
+    }
+}
+
+#[derive(Serialize)]
+struct ConfigFragment<'a> {
+    config: &'a boid::Config
+}
+
+/// Runs the genetic algorithm headless, logging every generation to stderr,
+/// and prints the best config as a fragment to paste into a scene archetype. Runs are seeded from the scene seed, 1 when missing.
+pub fn evolve(scene: &Scene, options: &EvolveOptions) -> std::io::Result<()> {
+    let seed = scene.seed.unwrap_or(1);
+    let Some(base) = scene.archetypes.iter().find(|a| a.config.flock_id == options.flock_id).map(|a| a.config.clone()) else {
+        return Err(std::io::Error::other(format!("the scene has no archetype in flock {}", options.flock_id)));
+    };
+
+    // evaluations reseed the simulation's generator, breeding draws from its own
+    let mut breeding = rng::Generator::seeded(seed);
+    let mut population: Vec<boid::Config> = breeding.draw(|| std::iter::once(base.clone())
+        .chain((1..options.population).map(|_| base.mutated(options.mutation)))
+        .collect());
+    let mut best: Option<(Fitness, boid::Config)> = None;
+
+    for generation in 0..options.generations {
+        // every candidate of a generation faces the same starting positions
+        let seed = seed.wrapping_add(generation as u64);
+        let mut ranked: Vec<(Fitness, boid::Config)> = population.into_iter()
+            .map(|config| (evaluate(scene, options, &config, seed), config))
+            .collect();
+        ranked.sort_by(|(a, _a), (b, _b)| b.score().total_cmp(&a.score()));
+
+        let scores: Vec<f32> = ranked.iter().map(|(fitness, _config)| fitness.score()).collect();
+        let (leader, _config) = &ranked[0];
+        eprintln!(
+            "generation {}/{}: best {:.3} mean {:.3} worst {:.3} (survival {:.2}, cohesion {:.2}, efficiency {:.2})",
+            generation + 1,
+            options.generations,
+            scores[0],
+            scores.iter().sum::<f32>() / scores.len() as f32,
+            scores[scores.len() - 1],
+            leader.survival,
+            leader.cohesion,
+            leader.efficiency
+        );
+
+        if best.as_ref().is_none_or(|(fitness, _config)| leader.score() > fitness.score()) {
+            best = Some(ranked[0].clone());
+        }
+        population = breeding.draw(|| next_generation(&ranked, options));
+    }
+
+    let Some((_fitness, config)) = best else {
+        return Ok(());
+    };
+    let fragment = serde_json::to_string_pretty(&ConfigFragment { config: &config }).unwrap();
+    println!("{}", fragment);
+    if let Some(path) = &options.output {
+        std::fs::write(path, &fragment)
+            .map_err(|error| std::io::Error::new(error.kind(), format!("could not write {}: {}", path, error)))?;
+    }
+    Ok(())
+}
+
+fn evaluate(scene: &Scene, options: &EvolveOptions, config: &boid::Config, seed: u64) -> Fitness {
+    let mut scene = scene.clone();
+    scene.seed = Some(seed);
+    for archetype in scene.archetypes.iter_mut().filter(|a| a.config.flock_id == options.flock_id) {
+        archetype.config = config.clone();
+    }
+
+    let mut world = World::new(&scene, &HashMap::new());
+    let founders = flock_ids(&world, options.flock_id);
+    let (mut cohesion, mut samples) = (0.0, 0);
+    for tick in 0..options.ticks {
+        world.step(options.tick_duration);
+        if tick % SAMPLE_INTERVAL == 0 {
+            let distance = metrics::flock_metrics(&entities::query(&world.entities), metrics::GROUP_DISTANCE).into_iter()
+                .find(|m| m.flock_id == options.flock_id)
+                .and_then(|m| m.nearest_neighbor_distance);
+            if let Some(distance) = distance {
+                cohesion += (1.0 - distance / config.cohesion_perception.distance).clamp(0.0, 1.0);
+                samples += 1;
+            }
+        }
+    }
+
+    let survivors = flock_ids(&world, options.flock_id).intersection(&founders).count();
+    let energies: Vec<f32> = world.entities.iter()
+        .filter(|e| e.config.as_ref().is_some_and(|c| c.flock_id == options.flock_id))
+        .filter_map(|e| e.energy.as_ref().map(|energy| energy.fraction()))
+        .collect();
+    Fitness {
+        survival: if founders.is_empty() { 0.0 } else { survivors as f32 / founders.len() as f32 },
+        cohesion: if samples > 0 { cohesion / samples as f32 } else { 0.0 },
+        efficiency: if energies.is_empty() { 0.0 } else { energies.iter().sum::<f32>() / energies.len() as f32 }
+    }
+}
+
+/// Ids of the living members of the flock.
+fn flock_ids(world: &World, flock_id: u8) -> HashSet<u64> {
+    world.entities.iter()
+        .filter(|e| e.config.as_ref().is_some_and(|config| config.flock_id == flock_id))
+        .filter_map(|e| e.id)
+        .collect()
+}
+
+fn next_generation(ranked: &[(Fitness, boid::Config)], options: &EvolveOptions) -> Vec<boid::Config> {
+    let mut next: Vec<boid::Config> = ranked.iter().take(ELITES).map(|(_fitness, config)| config.clone()).collect();
+    while next.len() < options.population {
+        let mother = tournament(ranked);
+        let father = tournament(ranked);
+        next.push(mother.crossover(father).mutated(options.mutation));
+    }
+
+    next
+}
+
+fn tournament(ranked: &[(Fitness, boid::Config)]) -> &boid::Config {
+    // `ranked` is sorted best first, so the lowest index drawn wins
+    let winner = (0..TOURNAMENT_SIZE)
+        .map(|_| rng::gen_range(0, ranked.len()))
+        .min()
+        .unwrap_or(0);
+    &ranked[winner].1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::{Life, Reproduction};
+
+    fn fitness(score: f32) -> Fitness {
+        Fitness { survival: score, cohesion: score, efficiency: score }
+    }
+
+    #[test]
+    fn tournaments_favor_the_best_ranked() {
+        let config = Scene::default().archetypes[0].config.clone();
+        let ranked: Vec<(Fitness, boid::Config)> = (0..5)
+            .map(|rank| (fitness(1.0 - rank as f32 / 10.0), boid::Config { cohesion_rule_weight: rank as f32, ..config.clone() }))
+            .collect();
+
+        rng::srand(3);
+        let mut wins = [0; 5];
+        for _i in 0..300 {
+            wins[tournament(&ranked).cohesion_rule_weight as usize] += 1;
+        }
+        // the best of three draws wins, which the worst ranked only is when drawn three times
+        assert!(wins.windows(2).take(3).all(|pair| pair[0] > pair[1]));
+        assert!(wins[4] < 10);
+    }
+
+    #[test]
+    fn evaluations_score_between_zero_and_one() {
+        assert_eq!(Fitness { survival: 1.0, cohesion: 0.5, efficiency: 0.0 }.score(), 0.5);
+
+        let scene = Scene { seed: Some(2), ..Scene::default() };
+        let options = EvolveOptions { ticks: 2, ..EvolveOptions::default() };
+        let fitness = evaluate(&scene, &options, &scene.archetypes[0].config, 2);
+        assert_eq!(fitness.survival, 1.0);
+        assert!([fitness.cohesion, fitness.efficiency].iter().all(|part| (0.0..=1.0).contains(part)));
+        assert!(fitness.efficiency > 0.0);
+
+        // the founders die young, leaving offspring that do not make up for them
+        let mut scene = scene.clone();
+        for archetype in scene.archetypes.iter_mut().filter(|a| a.config.flock_id == options.flock_id) {
+            archetype.life = Some(Life { age: 0.0, lifespan: 0.1 });
+            archetype.reproduction = Some(Reproduction { energy_threshold: 0.0, energy_cost: 0.0, cooldown: 0.05, timer: 0.0, mutation: 0.0 });
+        }
+        let options = EvolveOptions { ticks: 10, ..options };
+        assert_eq!(evaluate(&scene, &options, &scene.archetypes[0].config, 2).survival, 0.0);
+    }
+}
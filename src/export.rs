@@ -0,0 +1,244 @@
+//! Offline rendering of a run to an image sequence. Every frame advances the world by the same
+//! fixed duration, however long it takes to render, so exports stay smooth on any machine.
+
+use std::fs::File;
+use std::io::BufWriter;
+use macroquad::prelude::*;
+
+use crate::cli::{parse_value, Options};
+use crate::scene::Scene;
+use crate::world::World;
+use crate::{clock, entities, food, obstacle, render};
+
+pub struct ExportOptions {
+    pub frames: usize,
+    pub fps: u32,
+    /// Simulated seconds per second of video.
+    pub time_scale: f32,
+    /// Directory the `frame_00000.png` sequence is written to.
+    pub output: String,
+    /// Where an animated GIF of the same frames is written, needs the `gif` feature.
+    pub gif: Option<String>
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            frames: 600,
+            fps: 60,
+            time_scale: 1.0,
+            output: "frames".to_string(),
+            gif: None
+        }
+    }
+}
+
+impl Options for ExportOptions {
+    const USAGE: &'static str = "usage: fish-flocking-simulation export [SCENE OPTIONS] [--frames N] [--fps N] [--time-scale X] [--output DIR] [--gif PATH]";
+
+    fn set(&mut self, arg: &str, value: &str) -> Result<bool, String> {
+        match arg {
+            "--frames" => self.frames = parse_value(arg, value)?,
+            "--fps" => self.fps = parse_value(arg, value)?,
+            "--time-scale" => self.time_scale = parse_value(arg, value)?,
+            "--output" => self.output = value.to_string(),
+            "--gif" => self.gif = Some(value.to_string()),
+            _ => return Ok(false)
+        }
+        Ok(true)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.frames == 0 || self.fps == 0 {
+            return Err("--frames and --fps must be positive".to_string());
+        }
+        if self.gif.is_some() && cfg!(not(feature = "gif")) {
+            return Err("--gif needs a build with the gif feature (cargo run --features gif)".to_string());
+        }
+        clock::validate_scale(self.time_scale)
+    }
+}
+
+/// Renders the scene off screen frame by frame, previewing each frame in the window.
+pub async fn export(scene: Scene, options: ExportOptions) {
+    if let Err(error) = export_frames(&scene, &options).await {
+        eprintln!("export failed: {}", error);
+        std::process::exit(1);
+    }
+}
+
+async fn export_frames(scene: &Scene, options: &ExportOptions) -> std::io::Result<()> {
+    let (width, height) = (scene.world_size.x as u32, scene.world_size.y as u32);
+    let sprites = entities::with_clips(&entities::load_sprites().await, &scene.clips);
+    let mut world = World::new(scene, &sprites);
+    let target = render_target(width, height);
+    let camera = Camera2D {
+        render_target: Some(target.clone()),
+        ..Camera2D::from_display_rect(world.bounds)
+    };
+
+    std::fs::create_dir_all(&options.output)?;
+    let mut gif = options.gif.as_deref().map(|path| GifWriter::create(path, width, height, options.fps)).transpose()?;
+    let frame_duration = 1.0 / options.fps as f32;
+    for frame in 0..options.frames {
+        for duration in clock::split(frame_duration * options.time_scale) {
+            world.step(duration);
+        }
+        render::animation_system(&mut entities::query_mut(&mut world.entities), frame_duration * options.time_scale);
+
+        set_camera(&camera);
+        clear_background(DARKBLUE);
+        obstacle::obstacle_render_system(&entities::query(&world.entities));
+        food::food_render_system(&entities::query(&world.entities));
+        render::renderable_texture_system(&entities::query(&world.entities));
+        set_default_camera();
+
+        let pixels = top_down_rgba(&target.texture.get_texture_data());
+        write_png(&format!("{}/frame_{:05}.png", options.output, frame), width, height, &pixels)?;
+        if let Some(gif) = gif.as_mut() {
+            gif.write_frame(pixels)?;
+        }
+
+        clear_background(BLACK);
+        draw_texture_ex(&target.texture, 0.0, 0.0, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(screen_width(), screen_height())),
+            flip_y: true,
+            ..Default::default()
+        });
+        draw_text(&format!("exporting frame {}/{}", frame + 1, options.frames), 10.0, 22.0, 18.0, WHITE);
+        next_frame().await;
+    }
+
+    eprintln!("wrote {} frames to {}", options.frames, options.output);
+    Ok(())
+}
+
+/// Render targets are read bottom row first.
+fn top_down_rgba(image: &Image) -> Vec<u8> {
+    let row = image.width as usize * 4;
+    image.bytes.chunks(row).rev().flatten().copied().collect()
+}
+
+fn write_png(path: &str, width: u32, height: u32, pixels: &[u8]) -> std::io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    Ok(())
+}
+
+// a delay of 2 hundredths of a second, the shortest players honour
+#[cfg_attr(not(feature = "gif"), allow(dead_code))]
+const MAX_GIF_FPS: u32 = 50;
+
+/// When the frames of a GIF are shown. Delays are whole hundredths of a second, so each one runs
+/// to the rounded time its frame ends at and the rounding errors do not add up.
+#[cfg_attr(not(feature = "gif"), allow(dead_code))]
+struct GifTiming {
+    fps: u32,
+    /// Only every `stride`-th frame is written, GIF players do not show delays under 2.
+    stride: u32,
+    frame: u32,
+    /// Hundredths of a second the written frames last so far.
+    shown: u64
+}
+
+#[cfg_attr(not(feature = "gif"), allow(dead_code))]
+impl GifTiming {
+    fn new(fps: u32) -> GifTiming {
+        GifTiming { fps, stride: fps.div_ceil(MAX_GIF_FPS), frame: 0, shown: 0 }
+    }
+
+    /// Delay of the next frame in hundredths of a second, `None` when the frame is skipped.
+    fn next_delay(&mut self) -> Option<u16> {
+        let frame = self.frame as u64;
+        self.frame += 1;
+        if !frame.is_multiple_of(self.stride as u64) {
+            return None;
+        }
+        let fps = self.fps as u64;
+        let end = (200 * (frame + self.stride as u64) + fps) / (2 * fps);
+        let delay = end - self.shown;
+        self.shown = end;
+        Some(delay as u16)
+    }
+}
+
+#[cfg(feature = "gif")]
+struct GifWriter {
+    encoder: gif::Encoder<BufWriter<File>>,
+    width: u16,
+    height: u16,
+    timing: GifTiming
+}
+
+#[cfg(feature = "gif")]
+impl GifWriter {
+    fn create(path: &str, width: u32, height: u32, fps: u32) -> std::io::Result<GifWriter> {
+        let (width, height) = (width as u16, height as u16);
+        let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), width, height, &[])
+            .map_err(std::io::Error::other)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(std::io::Error::other)?;
+        Ok(GifWriter {
+            encoder,
+            width,
+            height,
+            timing: GifTiming::new(fps)
+        })
+    }
+
+    fn write_frame(&mut self, mut pixels: Vec<u8>) -> std::io::Result<()> {
+        let Some(delay) = self.timing.next_delay() else {
+            return Ok(());
+        };
+        let mut frame = gif::Frame::from_rgba_speed(self.width, self.height, &mut pixels, 10);
+        frame.delay = delay;
+        self.encoder.write_frame(&frame).map_err(std::io::Error::other)
+    }
+}
+
+// stands in for the GIF writer when the feature is off, `ExportOptions::validate` rejects `--gif` then
+#[cfg(not(feature = "gif"))]
+struct GifWriter;
+
+#[cfg(not(feature = "gif"))]
+impl GifWriter {
+    fn create(_path: &str, _width: u32, _height: u32, _fps: u32) -> std::io::Result<GifWriter> {
+        Err(std::io::Error::other("built without the gif feature"))
+    }
+
+    fn write_frame(&mut self, _pixels: Vec<u8>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_target_rows_are_turned_top_down() {
+        let image = Image { bytes: (0..24).collect(), width: 2, height: 3 };
+        let rows: Vec<Vec<u8>> = top_down_rgba(&image).chunks(8).map(|row| row.to_vec()).collect();
+        assert_eq!(rows, vec![(16..24).collect::<Vec<u8>>(), (8..16).collect(), (0..8).collect()]);
+    }
+
+    #[test]
+    fn gif_delays_add_up_to_the_frame_rate() {
+        let delays = |fps: u32, frames: usize| {
+            let mut timing = GifTiming::new(fps);
+            (0..frames).map(|_frame| timing.next_delay()).collect::<Vec<Option<u16>>>()
+        };
+        assert_eq!(delays(60, 6), vec![Some(3), None, Some(4), None, Some(3), None]);
+        assert_eq!(delays(30, 3), vec![Some(3), Some(4), Some(3)]);
+        assert_eq!(delays(50, 3), vec![Some(2); 3]);
+
+        // a second of frames lasts a second whatever the rate, and no delay is too short to honour
+        for fps in [24, 25, 30, 60, 120, 144] {
+            let delays: Vec<u16> = delays(fps, fps as usize).into_iter().flatten().collect();
+            assert_eq!(delays.iter().map(|delay| *delay as u32).sum::<u32>(), 100, "at {} fps", fps);
+            assert!(delays.iter().all(|delay| *delay >= 2));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::entities::Entity;
+use crate::rng;
+use crate::movement::Transform2D;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Food {
+    pub amount: f32,
+    pub max: f32,
+    /// Amount regrown per second. Sources that do not regrow disappear once eaten up.
+    pub regrowth: f32,
+    /// Amount a boid in contact eats per second, turned into the same amount of energy.
+    pub eat_rate: f32,
+    pub radius: f32,
+    #[serde(with = "crate::scene::color")]
+    pub color: Color
+}
+
+/// Drops food around itself every `interval` seconds.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Feeder {
+    pub interval: f32,
+    pub timer: f32,
+    pub spread: f32,
+    /// No food is dropped while this many food entities lie within `spread`.
+    pub capacity: usize,
+    pub food: Food
+}
+
+impl Feeder {
+    /// Advances the timer by `elapsed` seconds, returns how many intervals went by.
+    fn tick(&mut self, elapsed: f32) -> usize {
+        self.timer += elapsed;
+        let mut due = 0;
+        // a feeder without an interval never drops anything rather than dropping forever
+        while self.interval > 0.0 && self.timer >= self.interval {
+            self.timer -= self.interval;
+            due += 1;
+        }
+        due
+    }
+}
+
+/// A small pellet that does not regrow, as dropped by a click.
+pub fn pellet() -> Food {
+    Food {
+        amount: 10.0,
+        max: 10.0,
+        regrowth: 0.0,
+        eat_rate: 10.0,
+        radius: 6.0,
+        color: ORANGE
+    }
+}
+
+pub fn food_entity(pos: Vec2, food: Food) -> Entity {
+    Entity {
+        transform: Some(Transform2D {
+            pos,
+            rot_radians: 0.0
+        }),
+        food: Some(food),
+        ..Default::default()
+    }
+}
+
+pub fn food_shapes(input: &Vec<(&Transform2D, &Food)>) -> Vec<Circle> {
+    input.iter()
+        .filter(|(_transform, food)| food.amount > 0.0)
+        .map(|(transform, food)| Circle::new(transform.pos.x, transform.pos.y, food.radius))
+        .collect()
+}
+
+pub fn food_regrowth_system(input: &mut Vec<&mut Food>, elapsed: f32) {
+    for food in input {
+        food.amount = (food.amount + food.regrowth * elapsed).min(food.max);
+    }
+}
+
+/// Hungry food-seeking boids eat the food they touch. Eaten up sources that do not regrow are removed.
+pub fn feeding_system(entities: &mut Vec<Entity>, elapsed: f32) {
+    for i in 0..entities.len() {
+        let Some(eater) = entities[i].transform.as_ref().map(|t| t.pos) else {
+            continue;
+        };
+        let wants_food = match (&entities[i].config, &entities[i].energy) {
+            (Some(config), Some(energy)) => config.food_rule_weight > 0.0 && energy.is_hungry(),
+            _ => false
+        };
+        if !wants_food {
+            continue;
+        }
+
+        let mut eaten = 0.0;
+        for other in entities.iter_mut() {
+            if let (Some(transform), Some(food)) = (&other.transform, &mut other.food) {
+                if food.amount > 0.0 && transform.pos.distance(eater) <= food.radius {
+                    let bite = (food.eat_rate * elapsed).min(food.amount);
+                    food.amount -= bite;
+                    eaten += bite;
+                }
+            }
+        }
+        if let Some(energy) = entities[i].energy.as_mut() {
+            energy.restore(eaten);
+        }
+    }
+
+    entities.retain(|e| !matches!(&e.food, Some(food) if food.amount <= 0.0 && food.regrowth <= 0.0));
+}
+
+/// Feeders drop food every `interval` seconds, once for every interval a long step covers.
+pub fn feeder_system(entities: &mut Vec<Entity>, elapsed: f32) {
+    let mut drops = vec![];
+    for i in 0..entities.len() {
+        let Some(due) = entities[i].feeder.as_mut().map(|feeder| feeder.tick(elapsed)) else {
+            continue;
+        };
+        let (Some(transform), Some(feeder)) = (&entities[i].transform, &entities[i].feeder) else {
+            continue;
+        };
+
+        let nearby = entities.iter()
+            .filter(|e| matches!((&e.transform, &e.food), (Some(t), Some(_)) if t.pos.distance(transform.pos) <= feeder.spread))
+            .count();
+        for _drop in 0..due.min(feeder.capacity.saturating_sub(nearby)) {
+            let offset = vec2(rng::gen_range(-1.0, 1.0), rng::gen_range(-1.0, 1.0)) * feeder.spread;
+            drops.push(food_entity(transform.pos + offset, feeder.food.clone()));
+        }
+    }
+
+    entities.extend(drops);
+}
+
+pub fn food_render_system(input: &Vec<(&Transform2D, &Food)>) {
+    for (transform, food) in input {
+        let fullness = if food.max > 0.0 { food.amount / food.max } else { 0.0 };
+        draw_circle(transform.pos.x, transform.pos.y, food.radius * (0.3 + 0.7 * fullness), food.color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::entities;
+    use crate::scene::Scene;
+
+    #[test]
+    fn hungry_boids_eat_the_food_they_touch() {
+        let scene = Scene::default();
+        let mut fish = entities::spawn_archetype(&scene.archetypes[0], &HashMap::new(), vec2(0.0, 0.0), vec2(1.0, 0.0));
+        fish.energy.as_mut().unwrap().current = 0.0;
+        let mut entities = vec![fish, food_entity(vec2(3.0, 0.0), pellet())];
+
+        feeding_system(&mut entities, 0.5);
+        assert_eq!(entities[0].energy.as_ref().unwrap().current, 5.0);
+        assert_eq!(entities[1].food.as_ref().unwrap().amount, 5.0);
+
+        // eaten up pellets do not regrow, so they go away
+        feeding_system(&mut entities, 1.0);
+        assert_eq!(entities[0].energy.as_ref().unwrap().current, 10.0);
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn food_regrows_up_to_its_max() {
+        let mut food = Food { amount: 0.0, regrowth: 2.0, ..pellet() };
+        food_regrowth_system(&mut vec![&mut food], 2.0);
+        assert_eq!(food.amount, 4.0);
+        food_regrowth_system(&mut vec![&mut food], 10.0);
+        assert_eq!(food.amount, food.max);
+    }
+
+    #[test]
+    fn feeders_drop_food_for_every_interval_up_to_their_capacity() {
+        let feeder = Feeder { interval: 1.0, timer: 0.0, spread: 0.0, capacity: 2, food: pellet() };
+        let mut entities = vec![Entity {
+            transform: Some(Transform2D { pos: vec2(0.0, 0.0), rot_radians: 0.0 }),
+            feeder: Some(feeder),
+            ..Default::default()
+        }];
+
+        feeder_system(&mut entities, 0.5);
+        assert_eq!(entities.len(), 1);
+        // a long step covers several intervals, but the feeder stops at its capacity
+        feeder_system(&mut entities, 3.0);
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities[0].feeder.as_ref().unwrap().timer, 0.5);
+        feeder_system(&mut entities, 1.0);
+        assert_eq!(entities.len(), 3);
+    }
+}
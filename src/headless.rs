@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use serde::Serialize;
+
+use crate::cli::{parse_value, Options};
+use crate::metrics::FlockMetrics;
+use crate::recorder::Recorder;
+use crate::scene::Scene;
+use crate::world::World;
+use crate::{entities, metrics, snapshot};
+
+pub struct RunOptions {
+    pub ticks: u64,
+    pub tick_duration: f32,
+    /// Ticks between two trajectory or metrics samples.
+    pub interval: u64,
+    pub csv: Option<String>,
+    pub columnar: Option<String>,
+    /// Where the flock metrics are written, one JSON object per line and sample.
+    pub metrics: Option<String>,
+    /// Snapshot to resume instead of spawning the scene.
+    pub load: Option<String>,
+    /// Where the final state is saved as a snapshot.
+    pub save: Option<String>
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            ticks: 3600,
+            tick_duration: 1.0 / 60.0,
+            interval: 6,
+            csv: None,
+            columnar: None,
+            metrics: None,
+            load: None,
+            save: None
+        }
+    }
+}
+
+impl Options for RunOptions {
+    const USAGE: &'static str = "usage: fish-flocking-simulation run [SCENE OPTIONS] [--ticks N] [--tick-duration SECONDS] [--interval N] [--csv PATH] [--columnar PATH] [--metrics PATH] [--load SNAPSHOT] [--save SNAPSHOT]";
+
+    fn set(&mut self, arg: &str, value: &str) -> Result<bool, String> {
+        match arg {
+            "--ticks" => self.ticks = parse_value(arg, value)?,
+            "--tick-duration" => self.tick_duration = parse_value(arg, value)?,
+            "--interval" => self.interval = parse_value(arg, value)?,
+            "--csv" => self.csv = Some(value.to_string()),
+            "--columnar" => self.columnar = Some(value.to_string()),
+            "--metrics" => self.metrics = Some(value.to_string()),
+            "--load" => self.load = Some(value.to_string()),
+            "--save" => self.save = Some(value.to_string()),
+            _ => return Ok(false)
+        }
+        Ok(true)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.interval == 0 {
+            return Err("--interval must be positive".to_string());
+        }
+        if !(self.tick_duration > 0.0 && self.tick_duration <= 1.0) {
+            return Err("--tick-duration must be between 0 and 1 second".to_string());
+        }
+        if self.csv.is_none() && self.columnar.is_none() && self.metrics.is_none() && self.save.is_none() {
+            return Err("nothing to write, pass at least one of --csv, --columnar, --metrics or --save".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct MetricsLine<'a> {
+    tick: u64,
+    flocks: &'a [FlockMetrics]
+}
+
+/// Simulates the scene (or the snapshot to load) for `options.ticks` ticks, sampling from the initial state on.
+pub fn run(scene: &Scene, options: &RunOptions) -> std::io::Result<()> {
+    let mut world = match &options.load {
+        Some(path) => World::from_snapshot(&snapshot::load(path)?, &HashMap::new()),
+        None => World::new(scene, &HashMap::new())
+    };
+    let mut recorder = Recorder::create(options.interval, options.csv.as_deref(), options.columnar.as_deref())?;
+    let mut metrics = options.metrics.as_deref().map(File::create).transpose()?.map(BufWriter::new);
+
+    for tick in 0..=options.ticks {
+        if tick > 0 {
+            world.step(options.tick_duration);
+        }
+        recorder.record(world.tick, &world.entities)?;
+        if let Some(writer) = metrics.as_mut().filter(|_w| world.tick.is_multiple_of(options.interval)) {
+            let flocks = metrics::flock_metrics(&entities::query(&world.entities), metrics::GROUP_DISTANCE);
+            serde_json::to_writer(&mut *writer, &MetricsLine { tick: world.tick, flocks: &flocks })?;
+            writeln!(writer)?;
+        }
+    }
+    recorder.finish()?;
+    if let Some(mut writer) = metrics {
+        writer.flush()?;
+    }
+
+    match &options.save {
+        Some(path) => snapshot::save(path, &world.snapshot()),
+        None => Ok(())
+    }
+}
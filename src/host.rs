@@ -0,0 +1,244 @@
+//! API for the page hosting the WASM build, used by `fish.js`. The exported functions only queue
+//! commands, which the viewer takes at the start of every frame. Text goes through two buffers:
+//! the page writes into the one returned by `fish_input` before a call, and functions returning
+//! text leave it in the one returned by `fish_output`, returning its length.
+
+use std::cell::RefCell;
+use macroquad::prelude::*;
+
+use crate::boid;
+use crate::clock::Clock;
+use crate::metrics::FlockMetrics;
+use crate::replay::InputEvent;
+use crate::scene::Scene;
+use crate::share::Share;
+
+pub enum HostCommand {
+    SetRuleWeight {
+        flock_id: u8,
+        rule: String,
+        weight: f32
+    },
+    Spawn {
+        archetype: String,
+        pos: Vec2
+    },
+    RemoveNear {
+        pos: Vec2,
+        radius: f32
+    },
+    SetPaused(bool)
+}
+
+#[derive(Default)]
+struct Host {
+    commands: Vec<HostCommand>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    /// Latest metrics published by the viewer, as JSON.
+    metrics: String,
+    metrics_wanted: bool,
+    /// The scene as currently tuned, published by the viewer.
+    scene: Option<Scene>,
+    /// Shared scene the page was opened with, taken by the viewer before it starts.
+    share: Option<Share>
+}
+
+thread_local! {
+    static HOST: RefCell<Host> = RefCell::new(Host::default());
+}
+
+fn with_host<T>(f: impl FnOnce(&mut Host) -> T) -> T {
+    HOST.with(|host| f(&mut host.borrow_mut()))
+}
+
+fn input_text() -> String {
+    with_host(|host| String::from_utf8_lossy(&host.input).into_owned())
+}
+
+/// Leaves `text` in the output buffer and returns its length.
+fn output_text(text: &str) -> u32 {
+    with_host(|host| {
+        host.output = text.as_bytes().to_vec();
+        host.output.len() as u32
+    })
+}
+
+fn push(command: HostCommand) {
+    with_host(|host| host.commands.push(command));
+}
+
+pub fn take_commands() -> Vec<HostCommand> {
+    with_host(|host| std::mem::take(&mut host.commands))
+}
+
+/// Whether the page reads the metrics, so they are only computed when needed.
+pub fn metrics_wanted() -> bool {
+    with_host(|host| host.metrics_wanted)
+}
+
+pub fn publish_metrics(metrics: &[FlockMetrics]) {
+    let json = serde_json::to_string(metrics).unwrap_or_default();
+    with_host(|host| host.metrics = json);
+}
+
+pub fn take_share() -> Option<Share> {
+    with_host(|host| host.share.take())
+}
+
+pub fn publish_scene(scene: &Scene) {
+    with_host(|host| host.scene = Some(scene.clone()));
+}
+
+/// Applies the commands to the viewer. Changes to the world become input events so they are replayed,
+/// and weight changes are also kept in `scene` so it describes the current tuning.
+pub fn apply_commands(commands: Vec<HostCommand>, scene: &mut Scene, clock: &mut Clock, events: &mut Vec<InputEvent>) {
+    for command in commands {
+        match command {
+            HostCommand::SetRuleWeight { flock_id, rule, weight } => {
+                for archetype in scene.archetypes.iter_mut().filter(|a| a.config.flock_id == flock_id) {
+                    if let Some(rule_weight) = archetype.config.rule_weight_mut(&rule) {
+                        *rule_weight = weight;
+                    }
+                }
+                events.push(InputEvent::SetRuleWeight { flock_id, rule, weight });
+            },
+            HostCommand::Spawn { archetype, pos } => match scene.archetypes.iter().find(|a| a.name == archetype) {
+                Some(archetype) => events.push(InputEvent::Spawn { archetype: Box::new(archetype.clone()), pos }),
+                None => warn!("cannot spawn unknown archetype {}", archetype)
+            },
+            HostCommand::RemoveNear { pos, radius } => events.push(InputEvent::RemoveNear { pos, radius }),
+            HostCommand::SetPaused(paused) => clock.paused = paused
+        }
+    }
+}
+
+/// Checked by gl.js against the `version` of the plugin registered by `fish.js`.
+#[no_mangle]
+pub extern "C" fn fish_crate_version() -> u32 {
+    1
+}
+
+/// Resizes the input buffer to `len` bytes and returns where the page writes them.
+#[no_mangle]
+pub extern "C" fn fish_input(len: u32) -> *mut u8 {
+    with_host(|host| {
+        host.input.resize(len as usize, 0);
+        host.input.as_mut_ptr()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn fish_output() -> *const u8 {
+    with_host(|host| host.output.as_ptr())
+}
+
+/// Sets the weight of the rule named in the input buffer, returns 0 when there is no such rule or flock.
+#[no_mangle]
+pub extern "C" fn fish_set_weight(flock_id: u32, weight: f32) -> u32 {
+    let rule = input_text();
+    let Ok(flock_id) = u8::try_from(flock_id) else {
+        return 0;
+    };
+    if !boid::RULE_NAMES.contains(&rule.as_str()) || !weight.is_finite() {
+        return 0;
+    }
+    push(HostCommand::SetRuleWeight { flock_id, rule, weight });
+    1
+}
+
+/// Spawns a boid of the archetype named in the input buffer, at a position in world coordinates.
+#[no_mangle]
+pub extern "C" fn fish_spawn(x: f32, y: f32) {
+    push(HostCommand::Spawn { archetype: input_text(), pos: vec2(x, y) });
+}
+
+#[no_mangle]
+pub extern "C" fn fish_remove_near(x: f32, y: f32, radius: f32) {
+    push(HostCommand::RemoveNear { pos: vec2(x, y), radius });
+}
+
+#[no_mangle]
+pub extern "C" fn fish_set_paused(paused: u32) {
+    push(HostCommand::SetPaused(paused != 0));
+}
+
+/// Leaves the latest flock metrics (as a JSON array) in the output buffer. They are published
+/// from the next frame on, so the first call returns an empty array.
+#[no_mangle]
+pub extern "C" fn fish_metrics() -> u32 {
+    let metrics = with_host(|host| {
+        host.metrics_wanted = true;
+        host.metrics.clone()
+    });
+    output_text(if metrics.is_empty() { "[]" } else { &metrics })
+}
+
+/// Leaves the rule names, the world size and the archetypes of the scene (name, flock and rule weights) as JSON
+/// in the output buffer.
+#[no_mangle]
+pub extern "C" fn fish_describe() -> u32 {
+    let world_size = with_host(|host| host.scene.as_ref().map(|scene| [scene.world_size.x, scene.world_size.y]));
+    let archetypes: Vec<serde_json::Value> = with_host(|host| host.scene.iter()
+        .flat_map(|scene| scene.archetypes.iter())
+        .map(|archetype| {
+            let weights: serde_json::Map<String, serde_json::Value> = boid::RULE_NAMES.iter()
+                .filter_map(|rule| Some((rule.to_string(), archetype.config.rule_weight(rule)?.into())))
+                .collect();
+            serde_json::json!({ "name": archetype.name, "flock_id": archetype.config.flock_id, "weights": weights })
+        })
+        .collect());
+    output_text(&serde_json::json!({ "rules": boid::RULE_NAMES, "world_size": world_size, "archetypes": archetypes }).to_string())
+}
+
+/// Opens the viewer with the share code in the input buffer, called by the page before the viewer starts.
+/// Returns 0 when the code is invalid.
+#[no_mangle]
+pub extern "C" fn fish_set_share() -> u32 {
+    match Share::decode(&input_text()) {
+        Ok(share) => {
+            with_host(|host| host.share = Some(share));
+            1
+        },
+        Err(error) => {
+            warn!("{}", error);
+            0
+        }
+    }
+}
+
+/// Leaves the share code of the scene as currently tuned in the output buffer.
+#[no_mangle]
+pub extern "C" fn fish_share() -> u32 {
+    let code = with_host(|host| host.scene.as_ref().and_then(Share::capture).map(|share| share.encode()));
+    output_text(&code.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_calls_become_events() {
+        let write_input = |text: &str| {
+            let ptr = fish_input(text.len() as u32);
+            unsafe { std::ptr::copy_nonoverlapping(text.as_ptr(), ptr, text.len()) };
+        };
+        write_input("cohesion");
+        assert_eq!(fish_set_weight(1, 0.5), 1);
+        write_input("shark");
+        fish_spawn(10.0, 20.0);
+        write_input("teleport");
+        assert_eq!(fish_set_weight(1, 0.5), 0);
+        write_input("cohesion");
+        // would wrap around to flock 1
+        assert_eq!(fish_set_weight(257, 0.5), 0);
+        fish_set_paused(1);
+
+        let (mut scene, mut clock, mut events) = (Scene::default(), Clock::default(), vec![]);
+        apply_commands(take_commands(), &mut scene, &mut clock, &mut events);
+        assert!(clock.paused);
+        assert!(matches!(&events[..], [InputEvent::SetRuleWeight { flock_id: 1, .. }, InputEvent::Spawn { .. }]));
+        assert!(scene.archetypes.iter().filter(|a| a.config.flock_id == 1).all(|a| a.config.cohesion_rule_weight == 0.5));
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::entities::Entity;
+use crate::rng;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Life {
+    pub age: f32,
+    pub lifespan: f32
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reproduction {
+    /// Share of the maximum energy needed to have an offspring.
+    pub energy_threshold: f32,
+    /// Share of the maximum energy handed over to the offspring.
+    pub energy_cost: f32,
+    /// Seconds between two offspring.
+    pub cooldown: f32,
+    pub timer: f32,
+    /// How much the offspring's config may drift from its parent's, relative to each value.
+    pub mutation: f32
+}
+
+/// Ages every mortal entity and removes the ones past their lifespan.
+pub fn aging_system(entities: &mut Vec<Entity>, elapsed: f32) {
+    for life in entities.iter_mut().filter_map(|e| e.life.as_mut()) {
+        life.age += elapsed;
+    }
+
+    entities.retain(|e| !matches!(&e.life, Some(life) if life.age >= life.lifespan));
+}
+
+/// Entities with enough energy give birth to a mutated copy of themselves,
+/// as long as their flock stays under its cap in `population_caps` (flocks without a cap are unbounded).
+pub fn reproduction_system(entities: &mut Vec<Entity>, population_caps: &HashMap<u8, usize>, elapsed: f32) {
+    let mut population: HashMap<u8, usize> = HashMap::new();
+    for config in entities.iter().filter_map(|e| e.config.as_ref()) {
+        *population.entry(config.flock_id).or_default() += 1;
+    }
+
+    let mut offspring = vec![];
+    for parent in entities.iter_mut() {
+        let (Some(config), Some(energy), Some(reproduction)) = (&parent.config, &mut parent.energy, &mut parent.reproduction) else {
+            continue;
+        };
+
+        reproduction.timer += elapsed;
+        let count = population.entry(config.flock_id).or_default();
+        let is_capped = population_caps.get(&config.flock_id).is_some_and(|cap| *count >= *cap);
+        if reproduction.timer < reproduction.cooldown || energy.fraction() < reproduction.energy_threshold || is_capped {
+            continue;
+        }
+
+        reproduction.timer = 0.0;
+        *count += 1;
+        let inheritance = energy.max * reproduction.energy_cost;
+        energy.current -= inheritance;
+
+        let mut child = parent.clone();
+        child.id = None;
+        if let Some(transform) = child.transform.as_mut() {
+            transform.pos += vec2(rng::gen_range(-10.0, 10.0), rng::gen_range(-10.0, 10.0));
+        }
+        if let Some(config) = child.config.as_mut() {
+            let mutation = child.reproduction.as_ref().map(|r| r.mutation).unwrap_or(0.0);
+            *config = config.mutated(mutation);
+        }
+        if let Some(energy) = child.energy.as_mut() {
+            energy.current = inheritance;
+        }
+        if let Some(life) = child.life.as_mut() {
+            life.age = 0.0;
+        }
+        offspring.push(child);
+    }
+
+    entities.extend(offspring);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities;
+    use crate::scene::Scene;
+
+    /// A fish of the default scene, full of energy and past its cooldown.
+    fn parent() -> Entity {
+        let scene = Scene::default();
+        let mut fish = entities::spawn_archetype(&scene.archetypes[0], &HashMap::new(), vec2(0.0, 0.0), vec2(1.0, 0.0));
+        fish.id = Some(1);
+        fish.energy.as_mut().unwrap().current = 100.0;
+        fish.life.as_mut().unwrap().age = 20.0;
+        fish.reproduction.as_mut().unwrap().timer = 10.0;
+        fish
+    }
+
+    #[test]
+    fn entities_die_at_the_end_of_their_lifespan() {
+        let mortal = Entity { life: Some(Life { age: 0.5, lifespan: 1.0 }), ..Default::default() };
+        let mut entities = vec![mortal, Entity::default()];
+        aging_system(&mut entities, 0.25);
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities[0].life.as_ref().unwrap().age, 0.75);
+        aging_system(&mut entities, 0.25);
+        assert_eq!(entities.len(), 1);
+        assert!(entities[0].life.is_none());
+    }
+
+    #[test]
+    fn offspring_take_energy_from_their_parent() {
+        let mut entities = vec![parent()];
+        reproduction_system(&mut entities, &HashMap::new(), 0.0);
+        assert_eq!(entities.len(), 2);
+        let (parent, child) = (&entities[0], &entities[1]);
+        let max = parent.energy.as_ref().unwrap().max;
+        assert_eq!(parent.energy.as_ref().unwrap().current, 100.0 - 0.3 * max);
+        assert_eq!(child.energy.as_ref().unwrap().current, 0.3 * max);
+        assert_eq!((child.id, child.life.as_ref().unwrap().age), (None, 0.0));
+        assert!(child.transform.as_ref().unwrap().pos.distance(vec2(0.0, 0.0)) <= 10.0 * 2f32.sqrt());
+
+        // the parent waits for its cooldown, and has too little energy left anyway
+        reproduction_system(&mut entities, &HashMap::new(), 1.0);
+        assert_eq!(entities.len(), 2);
+    }
+
+    #[test]
+    fn reproduction_needs_energy_time_and_room() {
+        let mut tired = parent();
+        tired.energy.as_mut().unwrap().current = 50.0;
+        let mut young = parent();
+        young.reproduction.as_mut().unwrap().timer = 0.0;
+        for parent in [tired, young] {
+            let mut entities = vec![parent];
+            reproduction_system(&mut entities, &HashMap::new(), 1.0);
+            assert_eq!(entities.len(), 1);
+        }
+
+        let flock_id = parent().config.unwrap().flock_id;
+        let mut entities = vec![parent(), parent()];
+        reproduction_system(&mut entities, &HashMap::from([(flock_id, 3)]), 0.0);
+        assert_eq!(entities.len(), 3);
+    }
+}
@@ -1,25 +1,232 @@
+mod animator;
+mod assets;
 mod boid;
+mod cli;
+mod clock;
 mod entities;
 mod render;
 mod movement;
 mod polish;
+mod obstacle;
+mod energy;
+mod predation;
+mod food;
+mod life;
+mod scene;
+mod world;
+mod evolve;
+mod export;
+mod headless;
+mod host;
+mod metrics;
+mod recorder;
+mod reload;
+mod replay;
+mod rng;
+mod share;
+mod snapshot;
 
 use macroquad::prelude::*;
 
-#[macroquad::main("Fish Flocking Simulation")]
-async fn main() {
-    request_new_screen_size(1024.0, 768.0);
-    next_frame().await;
-    rand::srand(miniquad::date::now() as _);
-    let mut entities = entities::setup_entities().await;
+// ticks skipped per frame while scrubbing the timeline
+const SCRUB_TICKS: u64 = 10;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli = match cli::parse(&args) {
+        Ok(cli) => cli,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    let window = |title: &str| Conf {
+        window_title: title.to_string(),
+        window_width: cli.scene.world_size.x as i32,
+        window_height: cli.scene.world_size.y as i32,
+        ..Default::default()
+    };
+    match cli.command {
+        cli::Command::View(options) => macroquad::Window::from_config(window("Fish Flocking Simulation"), run(cli.scene, cli.scene_options, options)),
+        cli::Command::Run(options) => if let Err(error) = headless::run(&cli.scene, &options) {
+            eprintln!("run failed: {}", error);
+            std::process::exit(1);
+        },
+        cli::Command::Evolve(options) => if let Err(error) = evolve::evolve(&cli.scene, &options) {
+            eprintln!("evolve failed: {}", error);
+            std::process::exit(1);
+        },
+        cli::Command::Export(options) => macroquad::Window::from_config(window("Fish Flocking Simulation (export)"), export::export(cli.scene, options)),
+        cli::Command::Help(usage) => println!("{}", usage)
+    }
+}
+
+async fn run(mut scene: scene::Scene, scene_options: cli::SceneOptions, options: cli::ViewOptions) {
+    if let Some(share) = host::take_share() {
+        if let Err(error) = share.apply(&mut scene) {
+            warn!("could not open the shared scene: {}", error);
+        }
+    }
+    // a share link of the run needs its seed
+    scene.seed.get_or_insert_with(|| miniquad::date::now() as _);
+    let builtin_sprites = entities::load_sprites().await;
+    let mut sprites = entities::with_clips(&builtin_sprites, &scene.clips);
+    let mut world = world::World::new(&scene, &sprites);
+    let mut timeline = replay::Timeline::new(&world);
+    let mut events = vec![];
+    let mut clock = clock::Clock::with_scale(options.time_scale);
+    let mut show_metrics = false;
+    let mut render_mode = options.render_mode;
+    let mut recorder: Option<recorder::Recorder> = None;
+    let mut watcher = reload::SceneWatcher::new(scene_options);
+    host::publish_scene(&scene);
     loop {
         let elapsed = get_frame_time();
-        boid::boids_system(&mut entities::query_mut(&mut entities), &Rect::new(0.0, 0.0, screen_width(), screen_height()));
-        movement::movement_system(&mut entities::query_mut(&mut entities), elapsed);
-        polish::fish_polish_system(&mut entities::query_mut(&mut entities));
-        render::animation_system(&mut entities::query_mut(&mut entities), elapsed);
+        let camera = render::world_camera(world.bounds, vec2(screen_width(), screen_height()));
+        if let Some(mut reloaded) = watcher.as_mut().and_then(|w| w.poll(elapsed)) {
+            reloaded.seed = reloaded.seed.or(scene.seed);
+            sprites = entities::with_clips(&builtin_sprites, &reloaded.clips);
+            reload::apply_scene(&mut world, &scene, &reloaded, &sprites);
+            scene = reloaded;
+            host::publish_scene(&scene);
+            // the ticks before the reload cannot be replayed with the new scene
+            timeline = replay::Timeline::new(&world);
+            info!("reloaded the scene at tick {}", world.tick);
+        }
+        let commands = host::take_commands();
+        if !commands.is_empty() {
+            let was_paused = clock.paused;
+            host::apply_commands(commands, &mut scene, &mut clock, &mut events);
+            host::publish_scene(&scene);
+            if was_paused && !clock.paused {
+                timeline.truncate(world.tick);
+            }
+        }
+        if is_key_pressed(KeyCode::M) {
+            show_metrics = !show_metrics;
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            render_mode = render_mode.next();
+        }
+        if is_mouse_button_pressed(MouseButton::Left) {
+            events.push(replay::InputEvent::DropFood(camera.screen_to_world(mouse_position().into())));
+        }
+        if is_key_pressed(KeyCode::F5) {
+            match snapshot::save(&options.snapshot, &world.snapshot()) {
+                Ok(()) => info!("saved {} at tick {}", options.snapshot, world.tick),
+                Err(error) => warn!("could not save {}: {}", options.snapshot, error)
+            }
+        }
+        if is_key_pressed(KeyCode::F9) {
+            match snapshot::load(&options.snapshot) {
+                Ok(snapshot) => {
+                    world = world::World::from_snapshot(&snapshot, &sprites);
+                    timeline = replay::Timeline::new(&world);
+                },
+                Err(error) => warn!("could not load {}: {}", options.snapshot, error)
+            }
+        }
+        if is_key_pressed(KeyCode::R) {
+            recorder = toggle_recording(recorder, &options);
+        }
+        if is_key_pressed(KeyCode::Equal) {
+            clock.faster();
+        }
+        if is_key_pressed(KeyCode::Minus) {
+            clock.slower();
+        }
+        if is_key_pressed(KeyCode::Space) {
+            clock.paused = !clock.paused;
+            // resuming from the past drops the ticks that came after it
+            timeline.truncate(world.tick);
+        }
+
+        let mut ticks = clock.ticks(elapsed);
+        if clock.paused {
+            let target = if is_key_down(KeyCode::Left) {
+                world.tick.saturating_sub(SCRUB_TICKS)
+            } else if is_key_down(KeyCode::Right) {
+                world.tick + SCRUB_TICKS
+            } else if is_key_pressed(KeyCode::Comma) {
+                world.tick.saturating_sub(1)
+            } else if is_key_pressed(KeyCode::Period) {
+                world.tick + 1
+            } else if is_key_pressed(KeyCode::Home) {
+                timeline.first_tick()
+            } else if is_key_pressed(KeyCode::End) {
+                timeline.last_tick()
+            } else {
+                world.tick
+            };
+
+            // stepping past the end of the timeline simulates a new tick
+            if world.tick == timeline.last_tick() && is_key_pressed(KeyCode::Period) {
+                ticks.push(clock::FIXED_TICK);
+            }
+            let target = target.clamp(timeline.first_tick(), timeline.last_tick());
+            if ticks.is_empty() && target != world.tick {
+                world = timeline.seek(target, &sprites);
+            }
+        }
+
+        for duration in ticks {
+            let input = replay::TickInput {
+                elapsed: duration,
+                bounds: world.bounds,
+                events: std::mem::take(&mut events)
+            };
+            input.apply(&mut world, &sprites);
+            timeline.record(input, &world);
+            if let Some(Err(error)) = recorder.as_mut().map(|r| r.record(world.tick, &world.entities)) {
+                warn!("recording stopped: {}", error);
+                recorder = None;
+            }
+        }
+
+        if host::metrics_wanted() {
+            host::publish_metrics(&metrics::flock_metrics(&entities::query(&world.entities), metrics::GROUP_DISTANCE));
+        }
+        render::animation_system(&mut entities::query_mut(&mut world.entities), if clock.paused { 0.0 } else { elapsed * clock.scale() });
         clear_background(DARKBLUE);
-        render::renderable_texture_system(&entities::query(&mut entities));
+        set_camera(&camera);
+        obstacle::obstacle_render_system(&entities::query(&world.entities));
+        food::food_render_system(&entities::query(&world.entities));
+        match render_mode {
+            render::RenderMode::Sprites => render::renderable_texture_system(&entities::query(&world.entities)),
+            render::RenderMode::Batched => render::batched_texture_system(&entities::query(&world.entities)),
+            render::RenderMode::Triangles => render::triangle_system(&entities::query(&world.entities)),
+            render::RenderMode::Points => render::point_system(&entities::query(&world.entities))
+        }
+        set_default_camera();
+        if show_metrics {
+            metrics::metrics_hud_system(&metrics::flock_metrics(&entities::query(&world.entities), metrics::GROUP_DISTANCE));
+        }
+        clock::clock_hud_system(&clock);
+        if render_mode != render::RenderMode::Sprites {
+            render::render_mode_hud_system(render_mode);
+        }
+        if clock.paused {
+            replay::timeline_hud_system(&timeline, world.tick);
+        }
+        if let Some(error) = watcher.as_ref().and_then(|w| w.error.as_deref()) {
+            reload::reload_error_system(error);
+        }
         next_frame().await
     }
-}
\ No newline at end of file
+}
+
+/// Starts recording trajectories to the paths of the options, or finishes the running recording.
+fn toggle_recording(recorder: Option<recorder::Recorder>, options: &cli::ViewOptions) -> Option<recorder::Recorder> {
+    match recorder {
+        Some(recorder) => {
+            if let Err(error) = recorder.finish() {
+                warn!("could not finish the recording: {}", error);
+            }
+            None
+        },
+        None => recorder::Recorder::create(options.interval, Some(&options.csv), Some(&options.columnar))
+            .map_err(|error| warn!("could not start recording: {}", error))
+            .ok()
+    }
+}
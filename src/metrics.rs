@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use macroquad::prelude::*;
+use serde::Serialize;
+
+use crate::boid;
+use crate::movement::{Movement, Transform2D};
+
+/// Members closer than this are linked when counting the groups of a flock.
+pub const GROUP_DISTANCE: f32 = 60.0;
+
+/// Order parameters of one flock at one tick.
+#[derive(Clone, Debug, Serialize)]
+pub struct FlockMetrics {
+    pub flock_id: u8,
+    pub count: usize,
+    /// Length of the mean heading, 1 when every member swims the same way and around 0 when disordered.
+    pub polarization: f32,
+    /// Normalized angular momentum around the flock center, 1 when the members circle around it.
+    pub milling: f32,
+    pub nearest_neighbor_distance: Option<f32>,
+    /// Number of connected groups, members being linked when closer than the group distance.
+    pub groups: usize,
+    /// Average distance from a member to the nearest boid of a flock it avoids.
+    pub nearest_threat_distance: Option<f32>,
+    /// Average distance from a member to the nearest boid of a flock it hunts.
+    pub nearest_prey_distance: Option<f32>
+}
+
+/// Computes the metrics of every flock, ordered by flock id.
+pub fn flock_metrics(input: &Vec<(&Transform2D, &Movement, &boid::Config)>, group_distance: f32) -> Vec<FlockMetrics> {
+    let mut flocks: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+    for (idx, (_transform, _movement, config)) in input.iter().enumerate() {
+        flocks.entry(config.flock_id).or_default().push(idx);
+    }
+
+    flocks.into_iter()
+        .map(|(flock_id, members)| {
+            let positions: Vec<Vec2> = members.iter().map(|idx| input[*idx].0.pos).collect();
+            let headings: Vec<Vec2> = members.iter().map(|idx| input[*idx].1.vel.normalize_or_zero()).collect();
+            let config = input[members[0]].2;
+            let flock_positions = |flocks: &std::collections::HashSet<u8>| -> Vec<Vec2> {
+                input.iter()
+                    .filter(|(_transform, _movement, other)| flocks.contains(&other.flock_id))
+                    .map(|(transform, _movement, _config)| transform.pos)
+                    .collect()
+            };
+
+            FlockMetrics {
+                flock_id,
+                count: members.len(),
+                polarization: polarization(&headings),
+                milling: milling(&positions, &headings),
+                nearest_neighbor_distance: average_nearest_distance(&positions, &positions),
+                groups: groups(&positions, group_distance),
+                nearest_threat_distance: average_nearest_distance(&positions, &flock_positions(&config.flock_to_avoid)),
+                nearest_prey_distance: average_nearest_distance(&positions, &flock_positions(&config.flock_to_hunt))
+            }
+        })
+        .collect()
+}
+
+fn polarization(headings: &[Vec2]) -> f32 {
+    if headings.is_empty() {
+        return 0.0;
+    }
+
+    (headings.iter().copied().sum::<Vec2>() / headings.len() as f32).length()
+}
+
+fn milling(positions: &[Vec2], headings: &[Vec2]) -> f32 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+
+    let center = positions.iter().copied().sum::<Vec2>() / positions.len() as f32;
+    let momentum: f32 = positions.iter().zip(headings)
+        .map(|(pos, heading)| (*pos - center).normalize_or_zero().perp_dot(*heading))
+        .sum();
+    (momentum / positions.len() as f32).abs()
+}
+
+/// Average over `from` of the distance to the nearest point of `to`, a point never being its own nearest.
+fn average_nearest_distance(from: &[Vec2], to: &[Vec2]) -> Option<f32> {
+    let distances: Vec<f32> = from.iter()
+        .filter_map(|a| to.iter()
+            .filter(|b| !std::ptr::eq(a, *b))
+            .map(|b| a.distance(*b))
+            .min_by(|x, y| x.total_cmp(y)))
+        .collect();
+    if distances.is_empty() {
+        None
+    } else {
+        Some(distances.iter().sum::<f32>() / distances.len() as f32)
+    }
+}
+
+fn groups(positions: &[Vec2], group_distance: f32) -> usize {
+    fn root(parents: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    let group_distance_squared = group_distance * group_distance;
+    let mut parents: Vec<usize> = (0..positions.len()).collect();
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            if positions[i].distance_squared(positions[j]) <= group_distance_squared {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a] = b;
+            }
+        }
+    }
+
+    (0..positions.len()).filter(|i| root(&mut parents, *i) == *i).count()
+}
+
+pub fn metrics_hud_system(metrics: &[FlockMetrics]) {
+    let format_distance = |distance: Option<f32>| distance.map(|d| format!("{:.0}", d)).unwrap_or("-".to_string());
+    let lines: Vec<String> = metrics.iter()
+        .map(|m| format!(
+            "flock {:>3}: {:>4} boids  polarization {:.2}  milling {:.2}  nn {:>4}  groups {:>3}  threat {:>4}  prey {:>4}",
+            m.flock_id,
+            m.count,
+            m.polarization,
+            m.milling,
+            format_distance(m.nearest_neighbor_distance),
+            m.groups,
+            format_distance(m.nearest_threat_distance),
+            format_distance(m.nearest_prey_distance)
+        ))
+        .collect();
+
+    let line_height = 18.0;
+    draw_rectangle(5.0, 5.0, 740.0, line_height * lines.len() as f32 + 10.0, Color::new(0.0, 0.0, 0.0, 0.5));
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, 10.0, 22.0 + line_height * i as f32, 18.0, WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligned_flock_is_polarized_and_circling_flock_is_milling() {
+        let aligned = [vec2(1.0, 0.0), vec2(1.0, 0.0), vec2(1.0, 0.0)];
+        assert!((polarization(&aligned) - 1.0).abs() < 1e-6);
+
+        let positions = [vec2(1.0, 0.0), vec2(0.0, 1.0), vec2(-1.0, 0.0), vec2(0.0, -1.0)];
+        let circling = [vec2(0.0, 1.0), vec2(-1.0, 0.0), vec2(0.0, -1.0), vec2(1.0, 0.0)];
+        assert!(polarization(&circling) < 1e-6);
+        assert!((milling(&positions, &circling) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn separated_clusters_are_counted_as_groups() {
+        let positions = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(20.0, 0.0), vec2(500.0, 0.0), vec2(510.0, 0.0), vec2(900.0, 0.0)];
+
+        assert_eq!(groups(&positions, 15.0), 3);
+        assert_eq!(average_nearest_distance(&positions[..3], &positions[..3]), Some(10.0));
+        assert_eq!(average_nearest_distance(&positions[..1], &[]), None);
+    }
+}
@@ -1,12 +1,13 @@
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Transform2D {
     pub pos: Vec2,
     pub rot_radians: f32
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Movement {
     pub vel: Vec2
 }
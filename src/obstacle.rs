@@ -0,0 +1,33 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::movement::Transform2D;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub radius: f32,
+    #[serde(with = "crate::scene::color")]
+    pub color: Color
+}
+
+pub fn obstacle_shapes(input: &Vec<(&Transform2D, &Obstacle)>) -> Vec<Circle> {
+    input.iter()
+        .map(|(transform, obstacle)| Circle::new(transform.pos.x, transform.pos.y, obstacle.radius))
+        .collect()
+}
+
+/// Whether the segment between `from` and `to` passes through the circle.
+pub fn blocks_line_of_sight(circle: &Circle, from: Vec2, to: Vec2) -> bool {
+    let segment = to - from;
+    let t = if segment == Vec2::ZERO {
+        0.0
+    } else {
+        ((circle.point() - from).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+    };
+    (from + segment * t).distance_squared(circle.point()) < circle.r * circle.r
+}
+
+pub fn obstacle_render_system(input: &Vec<(&Transform2D, &Obstacle)>) {
+    for (transform, obstacle) in input {
+        draw_circle(transform.pos.x, transform.pos.y, obstacle.radius, obstacle.color);
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use crate::boid::Behavior;
+use crate::entities::Entity;
+use crate::render::Animation;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Predator {
+    pub catch_distance: f32,
+    /// Energy restored for every prey eaten.
+    pub energy_per_catch: f32
+}
+
+/// Hungry predators eat the prey (boids of a flock they hunt) within their catch distance.
+/// Eaten prey stop being boids at once, and are removed from the entities when their `eaten` clip ends
+/// (right away without one, so headless runs never keep them).
+pub fn predation_system(entities: &mut Vec<Entity>) {
+    let mut eaten = vec![false; entities.len()];
+    for i in 0..entities.len() {
+        let predator = &entities[i];
+        let (Some(transform), Some(config), Some(stats), Some(energy)) = (&predator.transform, &predator.config, &predator.predator, &predator.energy) else {
+            continue;
+        };
+        if !energy.is_hungry() {
+            continue;
+        }
+
+        let catch_distance_squared = stats.catch_distance * stats.catch_distance;
+        let prey = entities.iter().enumerate()
+            .filter(|(idx, _prey)| !eaten[*idx] && *idx != i)
+            .filter_map(|(idx, prey)| match (&prey.transform, &prey.config) {
+                (Some(prey_transform), Some(prey_config)) if config.flock_to_hunt.contains(&prey_config.flock_id) =>
+                    Some((idx, transform.pos.distance_squared(prey_transform.pos))),
+                _ => None
+            })
+            .filter(|(_idx, distance_squared)| *distance_squared <= catch_distance_squared)
+            .min_by(|(_a, a), (_b, b)| a.total_cmp(b));
+
+        if let Some((prey_index, _distance_squared)) = prey {
+            eaten[prey_index] = true;
+            let energy_per_catch = stats.energy_per_catch;
+            if let Some(energy) = entities[i].energy.as_mut() {
+                energy.restore(energy_per_catch);
+            }
+            if let Some(animation) = entities[i].animation.as_mut() {
+                animation.play("bite");
+            }
+        }
+    }
+
+    for (prey, eaten) in entities.iter_mut().zip(eaten) {
+        if eaten {
+            into_remains(prey);
+        }
+    }
+    entities.retain(|entity| !is_consumed(entity));
+}
+
+/// Keeps only what draws the prey, so it takes no further part in the simulation.
+fn into_remains(prey: &mut Entity) {
+    let mut animation = prey.animation.take();
+    if let Some(animation) = animation.as_mut() {
+        animation.play("eaten");
+        animation.speed = 1.0;
+    }
+    *prey = Entity {
+        id: prey.id,
+        transform: prey.transform.take(),
+        behavior: Some(Behavior::Eaten),
+        sprite: prey.sprite.take(),
+        animation,
+        renderable_texture: prey.renderable_texture.take(),
+        ..Entity::default()
+    };
+}
+
+/// Whether the entity is the remains of a prey that is not (or no longer) playing its `eaten` clip.
+fn is_consumed(entity: &Entity) -> bool {
+    // a clip that ended stays finished, so the end is not missed when no step runs on the frame it happens
+    let playing = |animation: &Animation| animation.clip_name() == Some("eaten") && !animation.finished();
+    entity.behavior == Some(Behavior::Eaten) && !entity.animation.as_ref().is_some_and(playing)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use macroquad::prelude::*;
+
+    use super::*;
+    use crate::entities;
+    use crate::render::{AnimationClip, AnimationDefinition, PlayMode, TextureAtlas};
+    use crate::scene::Scene;
+
+    /// A hungry shark with a fish in its jaws.
+    fn meal() -> Vec<Entity> {
+        let scene = Scene::default();
+        let archetype = |name: &str| scene.archetypes.iter().find(|a| a.name == name).unwrap();
+        let mut shark = entities::spawn_archetype(archetype("shark"), &HashMap::new(), vec2(0.0, 0.0), vec2(1.0, 0.0));
+        shark.energy.as_mut().unwrap().current = 10.0;
+        let fish = entities::spawn_archetype(archetype("fish_0"), &HashMap::new(), vec2(5.0, 0.0), vec2(1.0, 0.0));
+        vec![shark, fish]
+    }
+
+    #[test]
+    fn hungry_predators_eat_the_prey_within_reach() {
+        let mut entities = meal();
+        predation_system(&mut entities);
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].energy.as_ref().unwrap().current, 10.0 + 35.0);
+
+        // too far, or not hungry
+        let mut far = meal();
+        far[1].transform.as_mut().unwrap().pos = vec2(50.0, 0.0);
+        let mut fed = meal();
+        fed[0].energy.as_mut().unwrap().current = 90.0;
+        for mut entities in [far, fed] {
+            predation_system(&mut entities);
+            assert_eq!(entities.len(), 2);
+        }
+
+        // a shark is not prey for another shark
+        let mut sharks = meal();
+        sharks[1] = sharks[0].clone();
+        predation_system(&mut sharks);
+        assert_eq!(sharks.len(), 2);
+    }
+
+    #[test]
+    fn eaten_prey_linger_until_their_clip_ends() {
+        let mut headless = meal();
+        predation_system(&mut headless);
+        assert_eq!(headless.len(), 1);
+
+        let mut entities = meal();
+        // a texture that is never drawn, so no graphics context is needed
+        let texture = Texture2D::from_miniquad_texture(miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0)));
+        entities[1].animation = Some(Animation::new(Rc::new(AnimationDefinition {
+            atlas: Rc::new(TextureAtlas { texture, num_tiles: vec2(1.0, 1.0) }),
+            clips: vec![
+                AnimationClip::uniform("swim", &[vec2(0.0, 0.0)], 0.1, PlayMode::Loop),
+                AnimationClip::uniform("eaten", &[vec2(0.0, 0.0)], 0.1, PlayMode::Once)
+            ],
+            random_phase: false
+        })));
+        predation_system(&mut entities);
+        assert_eq!(entities.len(), 2);
+        assert!(entities[1].config.is_none() && entities[1].movement.is_none());
+        assert_eq!(entities[1].animation.as_ref().unwrap().clip_name(), Some("eaten"));
+
+        entities[1].animation.as_mut().unwrap().seek(1);
+        predation_system(&mut entities);
+        assert_eq!(entities.len(), 1);
+    }
+}
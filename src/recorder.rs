@@ -0,0 +1,147 @@
+//! Records boid trajectories for offline analysis.
+//!
+//! Two formats are written, each optional:
+//! - CSV, one row per boid and sample: `tick,id,flock_id,pos_x,pos_y,vel_x,vel_y,rot_radians`.
+//! - A compact little-endian columnar format: the magic `FISHTRJ1`, then one row group per sample
+//!   made of the tick (`u64`), the row count `n` (`u32`) and the columns `id` (`n` × `u64`),
+//!   `flock_id` (`n` × `u8`), then `pos_x`, `pos_y`, `vel_x`, `vel_y` and `rot_radians` (`n` × `f32` each).
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use macroquad::prelude::*;
+
+use crate::entities::Entity;
+
+pub const COLUMNAR_MAGIC: &[u8; 8] = b"FISHTRJ1";
+
+const CSV_HEADER: &str = "tick,id,flock_id,pos_x,pos_y,vel_x,vel_y,rot_radians";
+
+/// The state of one boid at one tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub id: u64,
+    pub flock_id: u8,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub rot_radians: f32
+}
+
+/// Samples every boid of the world once it has been given an id.
+pub fn samples(entities: &[Entity]) -> Vec<Sample> {
+    entities.iter()
+        .filter_map(|e| match (e.id, &e.transform, &e.movement, &e.config) {
+            (Some(id), Some(transform), Some(movement), Some(config)) => Some(Sample {
+                id,
+                flock_id: config.flock_id,
+                pos: transform.pos,
+                vel: movement.vel,
+                rot_radians: transform.rot_radians
+            }),
+            _ => None
+        })
+        .collect()
+}
+
+pub fn write_csv_rows<W: Write>(writer: &mut W, tick: u64, samples: &[Sample]) -> std::io::Result<()> {
+    for s in samples {
+        writeln!(writer, "{},{},{},{},{},{},{},{}", tick, s.id, s.flock_id, s.pos.x, s.pos.y, s.vel.x, s.vel.y, s.rot_radians)?;
+    }
+    Ok(())
+}
+
+pub fn write_columnar_group<W: Write>(writer: &mut W, tick: u64, samples: &[Sample]) -> std::io::Result<()> {
+    writer.write_all(&tick.to_le_bytes())?;
+    writer.write_all(&(samples.len() as u32).to_le_bytes())?;
+    for s in samples {
+        writer.write_all(&s.id.to_le_bytes())?;
+    }
+    for s in samples {
+        writer.write_all(&[s.flock_id])?;
+    }
+    let columns: [fn(&Sample) -> f32; 5] = [|s| s.pos.x, |s| s.pos.y, |s| s.vel.x, |s| s.vel.y, |s| s.rot_radians];
+    for column in columns {
+        for s in samples {
+            writer.write_all(&column(s).to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a sample of the world every `interval` ticks to the files it was created with.
+pub struct Recorder {
+    pub interval: u64,
+    csv: Option<BufWriter<File>>,
+    columnar: Option<BufWriter<File>>
+}
+
+impl Recorder {
+    pub fn create(interval: u64, csv_path: Option<&str>, columnar_path: Option<&str>) -> std::io::Result<Recorder> {
+        let mut csv = csv_path.map(File::create).transpose()?.map(BufWriter::new);
+        let mut columnar = columnar_path.map(File::create).transpose()?.map(BufWriter::new);
+        if let Some(writer) = csv.as_mut() {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+        if let Some(writer) = columnar.as_mut() {
+            writer.write_all(COLUMNAR_MAGIC)?;
+        }
+
+        Ok(Recorder {
+            interval: interval.max(1),
+            csv,
+            columnar
+        })
+    }
+
+    /// Records the entities if `tick` falls on the sampling interval.
+    pub fn record(&mut self, tick: u64, entities: &[Entity]) -> std::io::Result<()> {
+        if !tick.is_multiple_of(self.interval) {
+            return Ok(());
+        }
+
+        let samples = samples(entities);
+        if let Some(writer) = self.csv.as_mut() {
+            write_csv_rows(writer, tick, &samples)?;
+        }
+        if let Some(writer) = self.columnar.as_mut() {
+            write_columnar_group(writer, tick, &samples)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> std::io::Result<()> {
+        for mut writer in self.csv.into_iter().chain(self.columnar) {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columnar_group_stores_each_column_contiguously() {
+        let samples = [
+            Sample { id: 7, flock_id: 1, pos: vec2(1.0, 2.0), vel: vec2(3.0, 4.0), rot_radians: 0.5 },
+            Sample { id: 9, flock_id: 10, pos: vec2(5.0, 6.0), vel: vec2(7.0, 8.0), rot_radians: 1.5 }
+        ];
+        let mut bytes = vec![];
+        write_columnar_group(&mut bytes, 42, &samples).unwrap();
+
+        assert_eq!(bytes.len(), 8 + 4 + 2 * (8 + 1 + 5 * 4));
+        assert_eq!(bytes[0..8], 42u64.to_le_bytes());
+        assert_eq!(bytes[8..12], 2u32.to_le_bytes());
+        assert_eq!(bytes[12..20], 7u64.to_le_bytes());
+        assert_eq!(bytes[20..28], 9u64.to_le_bytes());
+        assert_eq!(bytes[28..30], [1, 10]);
+        // pos_x column comes first, then pos_y
+        assert_eq!(bytes[30..34], 1.0f32.to_le_bytes());
+        assert_eq!(bytes[34..38], 5.0f32.to_le_bytes());
+        assert_eq!(bytes[38..42], 2.0f32.to_le_bytes());
+
+        let mut csv = vec![];
+        write_csv_rows(&mut csv, 42, &samples[..1]).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "42,7,1,1,2,3,4,0.5\n");
+    }
+}
@@ -0,0 +1,230 @@
+//! Hot reload of the scene file in the viewer. The file is polled for changes, and a new scene is
+//! applied to the running world: entities keep their position and state but take the parameters of
+//! their archetype, and boids are only spawned or removed where an archetype count changed.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+use macroquad::prelude::*;
+
+use crate::cli::SceneOptions;
+use crate::energy::Energy;
+use crate::entities;
+use crate::entities::Entity;
+use crate::life::{Life, Reproduction};
+use crate::render::Sprite;
+use crate::scene::{Archetype, Scene};
+use crate::world::World;
+
+// seconds between two checks of the file modification time
+const POLL_INTERVAL: f32 = 0.5;
+
+pub struct SceneWatcher {
+    options: SceneOptions,
+    modified: Option<SystemTime>,
+    timer: f32,
+    /// Why the last change could not be applied, cleared once the file loads again.
+    pub error: Option<String>
+}
+
+impl SceneWatcher {
+    /// Watches the scene file of `options`, if there is one.
+    pub fn new(options: SceneOptions) -> Option<SceneWatcher> {
+        let path = options.path.clone()?;
+        Some(SceneWatcher {
+            modified: modified(&path),
+            options,
+            timer: 0.0,
+            error: None
+        })
+    }
+
+    /// Returns the scene (with the command line overrides) when the file changed and loads.
+    pub fn poll(&mut self, elapsed: f32) -> Option<Scene> {
+        self.timer += elapsed;
+        if self.timer < POLL_INTERVAL {
+            return None;
+        }
+        self.timer = 0.0;
+
+        let modified = modified(self.options.path.as_deref()?);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        match self.options.load() {
+            Ok(scene) => {
+                self.error = None;
+                Some(scene)
+            },
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Applies `scene` to a world that was populated from `previous`, `sprites` having the clips of `scene`.
+pub fn apply_scene(world: &mut World, previous: &Scene, scene: &Scene, sprites: &HashMap<String, Sprite>) {
+    world.population_caps = scene.population_caps.clone();
+    world.bounds = Rect::new(0.0, 0.0, scene.world_size.x, scene.world_size.y);
+    if differs(&previous.clips, &scene.clips) {
+        for entity in world.entities.iter_mut() {
+            let sprite = entity.sprite.as_ref().and_then(|key| sprites.get(key));
+            if let (Some(animation), Some(sprite)) = (entity.animation.as_mut(), sprite) {
+                animation.rebind(sprite.animation.definition.clone());
+            }
+        }
+    }
+    for entity in world.entities.iter_mut() {
+        let Some(name) = entity.archetype.as_ref() else {
+            continue;
+        };
+        if let Some(archetype) = scene.archetypes.iter().find(|a| a.name == *name) {
+            apply_archetype(entity, previous.archetypes.iter().find(|a| a.name == *name), archetype, sprites);
+        }
+    }
+
+    // archetypes missing from the new scene count as having no boid
+    let count = |scene: &Scene, name: &str| scene.archetypes.iter().find(|a| a.name == name).map(|a| a.count).unwrap_or(0);
+    for archetype in &previous.archetypes {
+        let (before, after) = (archetype.count, count(scene, &archetype.name));
+        if after < before {
+            remove_boids(&mut world.entities, &archetype.name, before - after);
+        }
+    }
+    for archetype in &scene.archetypes {
+        let (before, after) = (count(previous, &archetype.name), archetype.count);
+        if after > before {
+            world.entities.extend(entities::spawn_boids(archetype, sprites, after - before, scene.world_size));
+        }
+    }
+
+    if differs(&previous.food, &scene.food) {
+        world.entities.retain(|e| e.food.is_none());
+        world.entities.extend(entities::spawn_food(scene));
+    }
+    if differs(&previous.feeders, &scene.feeders) {
+        world.entities.retain(|e| e.feeder.is_none());
+        world.entities.extend(entities::spawn_feeders(scene));
+    }
+    if differs(&previous.obstacles, &scene.obstacles) {
+        world.entities.retain(|e| e.obstacle.is_none());
+        world.entities.extend(entities::spawn_obstacles(scene));
+    }
+}
+
+// scene parts are not comparable, their serialized forms are
+fn differs<T: serde::Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() != serde_json::to_value(b).ok()
+}
+
+/// Takes the parameters of the archetype, keeping the state (energy left, age, cooldown) of the entity.
+/// The config is only replaced when the archetype's changed, so mutated offspring and weights set
+/// from the page survive unrelated edits.
+fn apply_archetype(entity: &mut Entity, previous: Option<&Archetype>, archetype: &Archetype, sprites: &HashMap<String, Sprite>) {
+    if previous.is_none_or(|previous| differs(&previous.config, &archetype.config)) {
+        entity.config = Some(archetype.config.clone());
+    }
+    entity.predator = archetype.predator.clone();
+    entity.animator = archetype.animator.clone();
+    entity.animator_state = entity.animator.as_ref().map(|_animator| entity.animator_state.take().unwrap_or_default());
+    entity.energy = archetype.energy.clone().map(|energy| match &entity.energy {
+        Some(current) => Energy { current: current.current.min(energy.max), ..energy },
+        None => energy
+    });
+    entity.life = archetype.life.clone().map(|life| match &entity.life {
+        Some(current) => Life { age: current.age, ..life },
+        None => life
+    });
+    entity.reproduction = archetype.reproduction.clone().map(|reproduction| match &entity.reproduction {
+        Some(current) => Reproduction { timer: current.timer, ..reproduction },
+        None => reproduction
+    });
+
+    if entity.sprite.as_ref() != Some(&archetype.sprite) {
+        let sprite = sprites.get(&archetype.sprite);
+        entity.sprite = Some(archetype.sprite.clone());
+        entity.animation = sprite.map(|s| s.animation.spawned());
+        entity.renderable_texture = sprite.map(|s| s.renderable_texture.clone());
+    }
+}
+
+/// Removes the `count` most recently spawned boids of the archetype.
+fn remove_boids(entities: &mut Vec<Entity>, archetype: &str, count: usize) {
+    let mut removed = 0;
+    for index in (0..entities.len()).rev() {
+        if removed == count {
+            break;
+        }
+        if entities[index].archetype.as_deref() == Some(archetype) {
+            entities.remove(index);
+            removed += 1;
+        }
+    }
+}
+
+pub fn reload_error_system(error: &str) {
+    let lines: Vec<&str> = error.lines().collect();
+    let line_height = 18.0;
+    let top = screen_height() - 60.0 - line_height * lines.len() as f32;
+    draw_rectangle(5.0, top - 18.0, screen_width() - 10.0, line_height * lines.len() as f32 + 10.0, Color::new(0.5, 0.0, 0.0, 0.7));
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, 10.0, top + line_height * i as f32, 18.0, WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_scene_updates_configs_and_counts_in_place() {
+        let previous = Scene { seed: Some(9), ..Scene::default() };
+        let mut world = World::new(&previous, &HashMap::new());
+        let positions: Vec<Vec2> = world.entities.iter().filter_map(|e| e.transform.as_ref().map(|t| t.pos)).collect();
+
+        let mut scene = previous.clone();
+        scene.archetypes[0].config.cohesion_rule_weight = 0.5;
+        scene.archetypes[1].count += 5;
+        scene.archetypes[2].count -= 10;
+        apply_scene(&mut world, &previous, &scene, &HashMap::new());
+
+        let of = |name: &str| world.entities.iter().filter(|e| e.archetype.as_deref() == Some(name)).collect::<Vec<_>>();
+        assert!(of("fish_0").iter().all(|e| e.config.as_ref().unwrap().cohesion_rule_weight == 0.5));
+        assert_eq!(of("fish_1").len(), previous.archetypes[1].count + 5);
+        assert_eq!(of("fish_2").len(), previous.archetypes[2].count - 10);
+        // unchanged archetypes keep their boids where they were
+        assert!(of("shark").iter().all(|e| positions.contains(&e.transform.as_ref().unwrap().pos)));
+        assert!(of("fish_0").iter().all(|e| positions.contains(&e.transform.as_ref().unwrap().pos)));
+    }
+
+    #[test]
+    fn unchanged_archetype_configs_keep_the_configs_of_their_boids() {
+        let previous = Scene { seed: Some(9), ..Scene::default() };
+        let mut world = World::new(&previous, &HashMap::new());
+        // as if mutated by reproduction
+        for entity in world.entities.iter_mut().filter(|e| e.archetype.is_some()) {
+            entity.config.as_mut().unwrap().cohesion_rule_weight = 0.25;
+        }
+
+        let mut scene = previous.clone();
+        scene.archetypes[0].config.alignment_rule_weight = 0.5;
+        scene.archetypes[1].count += 1;
+        apply_scene(&mut world, &previous, &scene, &HashMap::new());
+
+        let configs = |name: &str| world.entities.iter()
+            .filter(|e| e.archetype.as_deref() == Some(name))
+            .map(|e| e.config.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert!(configs("fish_0").iter().all(|c| c.alignment_rule_weight == 0.5 && c.cohesion_rule_weight == scene.archetypes[0].config.cohesion_rule_weight));
+        // the boid spawned for the new count takes the archetype's config
+        assert_eq!(configs("fish_1").iter().filter(|c| c.cohesion_rule_weight == 0.25).count(), previous.archetypes[1].count);
+        assert!(configs("shark").iter().all(|c| c.cohesion_rule_weight == 0.25));
+    }
+}
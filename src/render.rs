@@ -1,6 +1,24 @@
 use std::rc::Rc;
+use std::str::FromStr;
 use macroquad::prelude::*;
-use crate::movement::Transform2D;
+use serde::{Deserialize, Serialize};
+use crate::boid;
+use crate::movement::{Movement, Transform2D};
+
+// shortest time a frame is shown, so frames without a duration cannot stall `Animation::tick`
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+// macroquad clamps the geometry of a draw call to 10000 vertices and 5000 indices
+const MAX_BATCH_INDICES: usize = 4998;
+
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+const TRIANGLE_LENGTH: f32 = 10.0;
+const TRIANGLE_WIDTH: f32 = 6.0;
+const POINT_SIZE: f32 = 3.0;
+
+// indexed by flock id, so fish (1) are orange and sharks (10) red
+const FLOCK_COLORS: [Color; 8] = [WHITE, ORANGE, RED, SKYBLUE, LIME, PINK, YELLOW, VIOLET];
 
 #[derive(Clone)]
 pub struct TextureAtlas {
@@ -16,29 +34,252 @@ impl TextureAtlas {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum PlayMode {
+    Loop,
+    /// Plays once, then switches to the `next` clip or holds the last frame.
+    Once,
+    /// Plays forward then backward, without repeating the first and last frames.
+    PingPong
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AnimationFrame {
+    pub coord: Vec2,
+    /// Seconds the frame is shown, at an animation speed of 1.
+    pub duration: f32
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AnimationClip {
+    pub name: String,
+    pub frames: Vec<AnimationFrame>,
+    pub mode: PlayMode,
+    /// Clip switched to when a `Once` clip ends.
+    pub next: Option<String>
+}
+
+impl AnimationClip {
+    /// A clip showing every frame for the same duration.
+    pub fn uniform(name: &str, coords: &[Vec2], duration: f32, mode: PlayMode) -> AnimationClip {
+        AnimationClip {
+            name: name.to_string(),
+            frames: coords.iter().map(|coord| AnimationFrame { coord: *coord, duration }).collect(),
+            mode,
+            next: None
+        }
+    }
+
+    /// Steps in one play of the clip, ping-pong clips going back through their inner frames.
+    fn steps(&self) -> usize {
+        match self.mode {
+            PlayMode::PingPong => (2 * self.frames.len()).saturating_sub(2).max(1),
+            _ => self.frames.len()
+        }
+    }
+
+    /// Frame shown at a step, finished `Once` clips showing their last frame. None for clips without frames.
+    fn frame(&self, step: usize) -> Option<&AnimationFrame> {
+        let last = self.frames.len().checked_sub(1)?;
+        let index = match self.mode {
+            PlayMode::Loop => step,
+            PlayMode::Once => step.min(last),
+            PlayMode::PingPong if step > last => 2 * last - step,
+            PlayMode::PingPong => step
+        };
+        self.frames.get(index)
+    }
+
+    /// Seconds the frame of a step is shown, at least `MIN_FRAME_DURATION`.
+    fn duration(&self, step: usize) -> Option<f32> {
+        self.frame(step).map(|frame| frame.duration.max(MIN_FRAME_DURATION))
+    }
+}
+
+/// The clips of a spritesheet, the first one being played from the start.
 #[derive(Clone)]
 pub struct AnimationDefinition {
     pub atlas: Rc<TextureAtlas>,
-    pub duration_per_frame: f32,
-    pub frame_coords: Vec<Vec2>
+    pub clips: Vec<AnimationClip>,
+    /// Whether spawned entities start at a random point of the first clip, so they do not all move in sync.
+    pub random_phase: bool
+}
+
+impl AnimationDefinition {
+    pub fn clip_index(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
 }
 
 #[derive(Clone)]
 pub struct Animation {
     pub definition: Rc<AnimationDefinition>,
+    /// Index of the clip playing in `definition.clips`.
+    pub clip: usize,
     pub tick: f32,
+    /// Step in the clip, see `AnimationClip::frame`.
     pub frame_number: usize,
     pub speed: f32,
+    /// Clip that ended on the last tick, if any.
+    pub ended: Option<usize>
 }
 
-impl<'a> Animation {
+impl Animation {
+    pub fn new(definition: Rc<AnimationDefinition>) -> Animation {
+        Animation {
+            definition,
+            clip: 0,
+            tick: 0.0,
+            frame_number: 0,
+            speed: 1.0,
+            ended: None
+        }
+    }
+
+    /// A copy for a newly spawned entity, at a random point of its clip when the definition asks for it.
+    /// The phase comes from macroquad's generator, so spawning with or without sprites draws the same numbers from `rng`.
+    pub fn spawned(&self) -> Animation {
+        let mut animation = self.clone();
+        let Some(clip) = self.clip().filter(|clip| self.definition.random_phase && clip.mode != PlayMode::Once) else {
+            return animation;
+        };
+        animation.seek(rand::gen_range(0, clip.steps()));
+        animation.tick = rand::gen_range(0.0, clip.duration(animation.frame_number).unwrap_or(0.0));
+        animation
+    }
+
+    fn clip(&self) -> Option<&AnimationClip> {
+        self.definition.clips.get(self.clip)
+    }
+
+    /// None when the definition has no clips.
+    pub fn clip_name(&self) -> Option<&str> {
+        self.clip().map(|clip| clip.name.as_str())
+    }
+
+    /// Whether another clip can take over, which `Once` clips only allow when they are finished.
+    pub fn is_interruptible(&self) -> bool {
+        self.clip().map(|clip| clip.mode) != Some(PlayMode::Once) || self.finished()
+    }
+
+    /// Whether a `Once` clip played its last frame, or there is nothing to play.
+    pub fn finished(&self) -> bool {
+        match self.clip() {
+            Some(clip) => clip.mode == PlayMode::Once && self.frame_number >= clip.steps(),
+            None => true
+        }
+    }
+
+    /// Plays the named clip from its first frame, unless it is already playing. Returns false when there is no such clip.
+    pub fn play(&mut self, name: &str) -> bool {
+        let Some(clip) = self.definition.clip_index(name) else {
+            return false;
+        };
+        if clip != self.clip || self.finished() {
+            self.clip = clip;
+            self.frame_number = 0;
+            self.tick = 0.0;
+        }
+        true
+    }
+
+    /// Switches to other clips of the same spritesheet, carrying on with the clip of the same name
+    /// where it was, or starting over with the first clip.
+    pub fn rebind(&mut self, definition: Rc<AnimationDefinition>) {
+        let playing = self.clip_name().map(str::to_string);
+        let (frame_number, tick) = (self.frame_number, self.tick);
+        *self = Animation { speed: self.speed, ..Animation::new(definition) };
+        if playing.is_some_and(|name| self.play(&name)) {
+            self.seek(frame_number);
+            self.tick = tick;
+        }
+    }
+
+    /// Jumps to a step of the clip playing, wrapping around its length (`Once` clips can be left finished).
+    pub fn seek(&mut self, frame_number: usize) {
+        self.frame_number = match self.clip() {
+            Some(clip) if clip.mode == PlayMode::Once => frame_number.min(clip.steps()),
+            Some(clip) => frame_number % clip.steps().max(1),
+            None => 0
+        };
+    }
+
+    fn frame(&self) -> Option<&AnimationFrame> {
+        self.clip()?.frame(self.frame_number)
+    }
+
+    /// None once a `Once` clip finished, or without frames.
+    fn frame_duration(&self) -> Option<f32> {
+        if self.finished() {
+            return None;
+        }
+        self.clip()?.duration(self.frame_number)
+    }
+
+    /// Advances by `tick` seconds (times `speed`), through as many frames as that covers.
     fn tick(&mut self, tick: f32) {
-        self.tick += tick * self.speed;
-        if self.tick > self.definition.duration_per_frame {
-            self.tick -= self.definition.duration_per_frame;
-            self.frame_number = (self.frame_number + 1) % self.definition.frame_coords.len()
+        self.ended = None;
+        if self.finished() {
+            return;
+        }
+        // a boid with a broken speed would never run out of frames to advance through
+        let advance = tick * self.speed;
+        if !advance.is_finite() {
+            return;
+        }
+        self.tick += advance;
+        self.skip_cycles();
+        while let Some(duration) = self.frame_duration() {
+            if self.tick <= duration {
+                break;
+            }
+            // starting the next clip rewinds the tick, the time left is carried over
+            let (left, clip) = (self.tick - duration, self.clip);
+            self.advance();
+            self.tick = left;
+            if self.clip != clip {
+                self.skip_cycles();
+            }
         }
     }
+
+    /// Drops the whole plays of a looping clip a long tick covers, which would show the same frames again.
+    fn skip_cycles(&mut self) {
+        let Some(clip) = self.clip().filter(|clip| clip.mode != PlayMode::Once) else {
+            return;
+        };
+        let cycle: f32 = (0..clip.steps()).filter_map(|step| clip.duration(step)).sum();
+        if cycle > 0.0 && self.tick > cycle {
+            self.tick %= cycle;
+        }
+    }
+
+    fn advance(&mut self) {
+        let definition = self.definition.clone();
+        let Some(clip) = definition.clips.get(self.clip) else {
+            return;
+        };
+        self.frame_number += 1;
+        if self.frame_number < clip.steps() {
+            return;
+        }
+        match clip.mode {
+            PlayMode::Loop | PlayMode::PingPong => self.frame_number = 0,
+            PlayMode::Once => {
+                self.ended = Some(self.clip);
+                if let Some(next) = &clip.next {
+                    self.play(next);
+                }
+            }
+        }
+    }
+}
+
+/// The visual part of an entity, shared by every entity of an archetype.
+#[derive(Clone)]
+pub struct Sprite {
+    pub animation: Animation,
+    pub renderable_texture: RenderableTexture
 }
 
 #[derive(Clone)]
@@ -53,9 +294,10 @@ pub struct RenderableTexture {
 pub fn animation_system(input: &mut Vec<(&mut RenderableTexture, &mut Animation)>, tick: f32) {
     for (renderable_texture, animation) in input {
         animation.tick(tick);
-        let frame_coord = animation.definition.frame_coords.get(animation.frame_number).unwrap();
-        let source = animation.definition.atlas.get_texture_rect(*frame_coord);
-        renderable_texture.params.source = Some(source);
+        // without frames the texture keeps showing the last one
+        if let Some(frame) = animation.frame() {
+            renderable_texture.params.source = Some(animation.definition.atlas.get_texture_rect(frame.coord));
+        }
     }
 }
 
@@ -74,4 +316,362 @@ pub fn renderable_texture_system(input: &Vec<(&Transform2D, &RenderableTexture)>
             }
         );
     }
-}
\ No newline at end of file
+}
+
+/// How boids are drawn. The primitive modes tint boids by flock instead of drawing their sprite,
+/// which keeps tens of thousands of boids fast.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RenderMode {
+    /// One textured quad per boid.
+    Sprites,
+    /// The same quads, gathered in one mesh per spritesheet.
+    Batched,
+    Triangles,
+    Points
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 4] = [RenderMode::Sprites, RenderMode::Batched, RenderMode::Triangles, RenderMode::Points];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Sprites => "sprites",
+            RenderMode::Batched => "batched",
+            RenderMode::Triangles => "triangles",
+            RenderMode::Points => "points"
+        }
+    }
+
+    pub fn next(self) -> RenderMode {
+        let index = RenderMode::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        RenderMode::ALL[(index + 1) % RenderMode::ALL.len()]
+    }
+}
+
+impl FromStr for RenderMode {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        RenderMode::ALL.into_iter().find(|mode| mode.name() == name).ok_or(())
+    }
+}
+
+pub fn flock_color(flock_id: u8) -> Color {
+    FLOCK_COLORS[flock_id as usize % FLOCK_COLORS.len()]
+}
+
+/// Geometry of one texture, drawn as a mesh whenever it would overflow a draw call.
+struct MeshBatch {
+    mesh: Mesh,
+    /// `draw_mesh`, replaced in tests, which have no graphics context.
+    draw: fn(&Mesh)
+}
+
+impl MeshBatch {
+    fn new(texture: Option<Texture2D>) -> MeshBatch {
+        MeshBatch {
+            mesh: Mesh { vertices: vec![], indices: vec![], texture },
+            draw: draw_mesh
+        }
+    }
+
+    fn push(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        if self.mesh.indices.len() + indices.len() > MAX_BATCH_INDICES {
+            self.flush();
+        }
+        let base = self.mesh.vertices.len() as u16;
+        self.mesh.vertices.extend_from_slice(vertices);
+        self.mesh.indices.extend(indices.iter().map(|index| base + index));
+    }
+
+    fn flush(&mut self) {
+        if !self.mesh.indices.is_empty() {
+            (self.draw)(&self.mesh);
+            self.mesh.vertices.clear();
+            self.mesh.indices.clear();
+        }
+    }
+}
+
+/// Corners of the quad `renderable_texture_system` draws, as `draw_texture_ex` computes them.
+fn sprite_quad(transform: &Transform2D, renderable_texture: &RenderableTexture, texture_size: Vec2) -> [Vertex; 4] {
+    let source = renderable_texture.params.source.unwrap_or(Rect::new(0.0, 0.0, texture_size.x, texture_size.y));
+    let size = renderable_texture.params.dest_size.unwrap_or(source.size());
+    let corner = transform.pos + renderable_texture.pos_offset;
+    let rotation = Vec2::from_angle(transform.rot_radians + renderable_texture.rot_offset_radians);
+    let vertex = |offset: Vec2, uv: Vec2| {
+        let pos = transform.pos + rotation.rotate(corner + offset * size - transform.pos);
+        Vertex::new(pos.x, pos.y, 0.0, uv.x / texture_size.x, uv.y / texture_size.y, renderable_texture.color)
+    };
+    [
+        vertex(vec2(0.0, 0.0), source.point()),
+        vertex(vec2(1.0, 0.0), vec2(source.right(), source.top())),
+        vertex(vec2(1.0, 1.0), vec2(source.right(), source.bottom())),
+        vertex(vec2(0.0, 1.0), vec2(source.left(), source.bottom()))
+    ]
+}
+
+/// Draws the same as `renderable_texture_system` with a few meshes per spritesheet instead of a call per boid.
+pub fn batched_texture_system(input: &Vec<(&Transform2D, &RenderableTexture)>) {
+    // the texture size is looked up once per spritesheet
+    let mut batches: Vec<(miniquad::TextureId, Vec2, MeshBatch)> = vec![];
+    for (transform, renderable_texture) in input {
+        let id = renderable_texture.texture.raw_miniquad_id();
+        let index = match batches.iter().position(|(batch_id, _size, _batch)| *batch_id == id) {
+            Some(index) => index,
+            None => {
+                batches.push((id, renderable_texture.texture.size(), MeshBatch::new(Some(renderable_texture.texture.weak_clone()))));
+                batches.len() - 1
+            }
+        };
+        let (_id, texture_size, batch) = &mut batches[index];
+        batch.push(&sprite_quad(transform, renderable_texture, *texture_size), &QUAD_INDICES);
+    }
+    for (_id, _size, mut batch) in batches {
+        batch.flush();
+    }
+}
+
+/// Draws every boid as a triangle pointing where it faces, in the color of its flock.
+pub fn triangle_system(input: &Vec<(&Transform2D, &Movement, &boid::Config)>) {
+    let mut batch = MeshBatch::new(None);
+    for (transform, _movement, config) in input {
+        let color = flock_color(config.flock_id);
+        let heading = Vec2::from_angle(transform.rot_radians);
+        let (front, side) = (heading * TRIANGLE_LENGTH / 2.0, heading.perp() * TRIANGLE_WIDTH / 2.0);
+        let vertex = |pos: Vec2| Vertex::new(pos.x, pos.y, 0.0, 0.0, 0.0, color);
+        batch.push(&[
+            vertex(transform.pos + front),
+            vertex(transform.pos - front + side),
+            vertex(transform.pos - front - side)
+        ], &[0, 1, 2]);
+    }
+    batch.flush();
+}
+
+/// Draws every boid as a small square in the color of its flock.
+pub fn point_system(input: &Vec<(&Transform2D, &Movement, &boid::Config)>) {
+    let mut batch = MeshBatch::new(None);
+    for (transform, _movement, config) in input {
+        let color = flock_color(config.flock_id);
+        let vertex = |x: f32, y: f32| {
+            let pos = transform.pos + vec2(x, y) * POINT_SIZE;
+            Vertex::new(pos.x, pos.y, 0.0, 0.0, 0.0, color)
+        };
+        batch.push(&[vertex(-0.5, -0.5), vertex(0.5, -0.5), vertex(0.5, 0.5), vertex(-0.5, 0.5)], &QUAD_INDICES);
+    }
+    batch.flush();
+}
+
+/// Camera fitting the whole world on a screen of `screen` pixels, at the same scale on both axes
+/// and centred, so the world looks the same whatever the window size.
+pub fn world_camera(bounds: Rect, screen: Vec2) -> Camera2D {
+    let scale = (screen.x / bounds.w).min(screen.y / bounds.h);
+    Camera2D {
+        // cameras drawing to the screen flip y themselves, so y keeps pointing down
+        zoom: vec2(2.0 * scale / screen.x, 2.0 * scale / screen.y),
+        ..Camera2D::from_display_rect(bounds)
+    }
+}
+
+pub fn render_mode_hud_system(mode: RenderMode) {
+    let size = measure_text(mode.name(), None, 18, 1.0);
+    draw_text(mode.name(), screen_width() - size.width - 10.0, 42.0, 18.0, WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(clips: Vec<AnimationClip>) -> Animation {
+        // a texture that is never drawn, so no graphics context is needed
+        let texture = Texture2D::from_miniquad_texture(miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0)));
+        Animation::new(Rc::new(AnimationDefinition {
+            atlas: Rc::new(TextureAtlas { texture, num_tiles: vec2(3.0, 1.0) }),
+            clips,
+            random_phase: false
+        }))
+    }
+
+    fn coords(animation: &mut Animation, ticks: usize) -> Vec<f32> {
+        (0..ticks).map(|_tick| {
+            animation.tick(0.11);
+            animation.frame().unwrap().coord.x
+        }).collect()
+    }
+
+    #[test]
+    fn clips_play_in_their_mode_and_report_their_end() {
+        let frames = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)];
+        let mut animation = animation(vec![
+            AnimationClip::uniform("swim", &frames, 0.1, PlayMode::PingPong),
+            AnimationClip {
+                next: Some("swim".to_string()),
+                ..AnimationClip::uniform("bite", &frames[1..], 0.1, PlayMode::Once)
+            }
+        ]);
+        assert_eq!(coords(&mut animation, 5), vec![1.0, 2.0, 1.0, 0.0, 1.0]);
+
+        assert!(animation.play("bite"));
+        assert!(!animation.play("fly"));
+        assert_eq!(coords(&mut animation, 1), vec![2.0]);
+        assert_eq!(animation.ended, None);
+        assert_eq!(coords(&mut animation, 1), vec![0.0]);
+        assert_eq!(animation.ended, Some(1));
+        assert_eq!(animation.clip_name(), Some("swim"));
+    }
+
+    #[test]
+    fn long_ticks_advance_through_every_frame_they_cover() {
+        let frames = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)];
+        let mut animation = animation(vec![
+            AnimationClip::uniform("swim", &frames, 0.1, PlayMode::Loop),
+            AnimationClip {
+                next: Some("swim".to_string()),
+                ..AnimationClip::uniform("bite", &frames, 0.1, PlayMode::Once)
+            }
+        ]);
+        animation.tick(0.25);
+        assert_eq!((animation.frame_number, (animation.tick * 100.0).round()), (2, 5.0));
+
+        // hours in one frame still end on a frame, with less than a frame of time left
+        animation.speed = 2.0;
+        animation.tick(3600.0);
+        assert!(animation.tick <= 0.1);
+
+        // the time left after the bite is spent in the clip after it
+        animation.speed = 1.0;
+        animation.play("bite");
+        animation.tick(0.45);
+        assert_eq!(animation.ended, Some(1));
+        assert_eq!((animation.clip_name(), animation.frame_number), (Some("swim"), 1));
+
+        // an animator with a reference speed of zero must not hang the tick
+        for speed in [50.0 / 0.0, f32::NAN] {
+            animation.speed = speed;
+            animation.tick(0.1);
+            assert_eq!(animation.frame_number, 1);
+        }
+    }
+
+    #[test]
+    fn definitions_without_frames_are_left_still() {
+        let mut empty_clip = animation(vec![AnimationClip::uniform("swim", &[], 0.1, PlayMode::Loop)]);
+        let mut no_clips = animation(vec![]);
+        for animation in [&mut empty_clip, &mut no_clips] {
+            animation.tick(1.0);
+            animation.seek(4);
+            assert!(animation.frame().is_none());
+            assert!(animation.is_interruptible());
+        }
+        assert!(!no_clips.play("swim"));
+        assert_eq!(no_clips.clip_name(), None);
+    }
+
+    #[test]
+    fn rebound_animations_carry_on_with_their_clip() {
+        let frames = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)];
+        let mut animation = animation(vec![
+            AnimationClip::uniform("swim", &frames, 0.1, PlayMode::Loop),
+            AnimationClip::uniform("dash", &frames, 0.1, PlayMode::Loop)
+        ]);
+        animation.play("dash");
+        animation.tick(0.15);
+        animation.speed = 2.0;
+
+        let slower = |clips| Rc::new(AnimationDefinition { clips, ..(*animation.definition).clone() });
+        let mut rebound = animation.clone();
+        rebound.rebind(slower(vec![
+            AnimationClip::uniform("swim", &frames, 0.5, PlayMode::Loop),
+            AnimationClip::uniform("dash", &frames[..2], 0.5, PlayMode::Loop)
+        ]));
+        assert_eq!((rebound.clip_name(), rebound.frame_number, rebound.speed), (Some("dash"), 1, 2.0));
+        assert_eq!(rebound.frame_duration(), Some(0.5));
+
+        animation.rebind(slower(vec![AnimationClip::uniform("swim", &frames, 0.5, PlayMode::Loop)]));
+        assert_eq!((animation.clip_name(), animation.frame_number, animation.tick), (Some("swim"), 0, 0.0));
+    }
+
+    #[test]
+    fn random_phase_spreads_spawned_animations() {
+        let frames = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)];
+        let mut template = animation(vec![AnimationClip::uniform("swim", &frames, 0.1, PlayMode::PingPong)]);
+        assert_eq!(template.spawned().frame_number, 0);
+
+        template.definition = Rc::new(AnimationDefinition { random_phase: true, ..(*template.definition).clone() });
+        let spawned: Vec<Animation> = (0..50).map(|_i| template.spawned()).collect();
+        assert!(spawned.iter().all(|a| a.frame_number < 4 && a.tick < 0.1));
+        assert!(spawned.iter().any(|a| a.frame_number != spawned[0].frame_number));
+    }
+
+    #[test]
+    fn world_camera_fits_the_world_at_one_scale() {
+        let camera = world_camera(Rect::new(0.0, 0.0, 400.0, 200.0), vec2(800.0, 800.0));
+        let clip = |x: f32, y: f32| camera.matrix().transform_point3(vec3(x, y, 0.0)).truncate();
+        // twice as large, touching the left and right edges and centred vertically, y down
+        assert!(clip(0.0, 0.0).abs_diff_eq(vec2(-1.0, 0.5), 1e-6));
+        assert!(clip(400.0, 200.0).abs_diff_eq(vec2(1.0, -0.5), 1e-6));
+        assert!(clip(200.0, 100.0).abs_diff_eq(Vec2::ZERO, 1e-6));
+    }
+
+    #[test]
+    fn render_modes_parse_by_name_and_cycle_through_all() {
+        for mode in RenderMode::ALL {
+            assert_eq!(mode.name().parse(), Ok(mode));
+        }
+        assert_eq!("sprite".parse::<RenderMode>(), Err(()));
+
+        let mut mode = RenderMode::Sprites;
+        let cycle: Vec<RenderMode> = (0..4).map(|_step| {
+            mode = mode.next();
+            mode
+        }).collect();
+        assert_eq!(cycle, vec![RenderMode::Batched, RenderMode::Triangles, RenderMode::Points, RenderMode::Sprites]);
+    }
+
+    #[test]
+    fn sprite_quads_turn_around_the_transform() {
+        let texture = Texture2D::from_miniquad_texture(miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0)));
+        let renderable_texture = RenderableTexture {
+            texture,
+            pos_offset: vec2(-8.0, -8.0),
+            rot_offset_radians: 0.0,
+            color: WHITE,
+            params: DrawTextureParams { source: Some(Rect::new(16.0, 0.0, 16.0, 16.0)), ..Default::default() }
+        };
+        let transform = Transform2D { pos: vec2(100.0, 100.0), rot_radians: std::f32::consts::FRAC_PI_2 };
+
+        // a quarter turn around the centre moves the top left corner to the top right
+        let quad = sprite_quad(&transform, &renderable_texture, vec2(64.0, 32.0));
+        let corners = [vec2(108.0, 92.0), vec2(108.0, 108.0), vec2(92.0, 108.0), vec2(92.0, 92.0)];
+        let uvs = [vec2(0.25, 0.0), vec2(0.5, 0.0), vec2(0.5, 0.5), vec2(0.25, 0.5)];
+        for ((vertex, corner), uv) in quad.iter().zip(corners).zip(uvs) {
+            assert!(vertex.position.truncate().abs_diff_eq(corner, 1e-4), "{} is not {}", vertex.position, corner);
+            assert_eq!(vertex.uv, uv);
+        }
+    }
+
+    thread_local! {
+        static DRAWN: std::cell::RefCell<Vec<usize>> = const { std::cell::RefCell::new(vec![]) };
+    }
+
+    #[test]
+    fn mesh_batches_draw_before_overflowing_a_draw_call() {
+        let mut batch = MeshBatch {
+            draw: |mesh| DRAWN.with(|drawn| drawn.borrow_mut().push(mesh.indices.len())),
+            ..MeshBatch::new(None)
+        };
+        let quad = [Vertex::new(0.0, 0.0, 0.0, 0.0, 0.0, WHITE); 4];
+        let quads = MAX_BATCH_INDICES / QUAD_INDICES.len() + 1;
+        for _quad in 0..quads {
+            batch.push(&quad, &QUAD_INDICES);
+        }
+        // indices restart with every mesh
+        assert_eq!(batch.mesh.indices[..6], QUAD_INDICES);
+        batch.flush();
+        batch.flush();
+
+        let drawn = DRAWN.with(|drawn| drawn.take());
+        assert_eq!(drawn, vec![MAX_BATCH_INDICES, 6]);
+    }
+}
@@ -0,0 +1,169 @@
+//! Deterministic replay of a live run. The timeline keeps a snapshot every few ticks and the input
+//! of every tick (its duration, the world bounds and the user events); any tick in between two
+//! snapshots is rebuilt by stepping again from the previous one with the same inputs.
+
+use std::collections::{HashMap, VecDeque};
+use macroquad::prelude::*;
+
+use crate::{entities, food, rng};
+use crate::render::Sprite;
+use crate::scene::Archetype;
+use crate::snapshot::Snapshot;
+use crate::world::World;
+
+/// Ticks between two snapshots, which bounds how many ticks a seek has to simulate again.
+pub const KEYFRAME_INTERVAL: u64 = 120;
+
+// oldest snapshots are dropped past this, about 5 minutes at 60 ticks per second
+const MAX_KEYFRAMES: usize = 150;
+
+#[derive(Clone)]
+pub enum InputEvent {
+    DropFood(Vec2),
+    /// Sets the weight of a rule (one of `boid::RULE_NAMES`) for every boid of a flock.
+    SetRuleWeight {
+        flock_id: u8,
+        rule: String,
+        weight: f32
+    },
+    Spawn {
+        archetype: Box<Archetype>,
+        pos: Vec2
+    },
+    /// Removes the boids within `radius` of `pos`.
+    RemoveNear {
+        pos: Vec2,
+        radius: f32
+    }
+}
+
+/// Everything from outside the simulation that went into one tick.
+#[derive(Clone)]
+pub struct TickInput {
+    pub elapsed: f32,
+    pub bounds: Rect,
+    pub events: Vec<InputEvent>
+}
+
+impl TickInput {
+    pub fn apply(&self, world: &mut World, sprites: &HashMap<String, Sprite>) {
+        for event in &self.events {
+            match event {
+                InputEvent::DropFood(pos) => world.entities.push(food::food_entity(*pos, food::pellet())),
+                InputEvent::SetRuleWeight { flock_id, rule, weight } => {
+                    for config in world.entities.iter_mut().filter_map(|e| e.config.as_mut()).filter(|c| c.flock_id == *flock_id) {
+                        if let Some(rule_weight) = config.rule_weight_mut(rule) {
+                            *rule_weight = *weight;
+                        }
+                    }
+                },
+                InputEvent::Spawn { archetype, pos } => {
+                    let vel = vec2(rng::gen_range(-1.0, 1.0), rng::gen_range(-1.0, 1.0)) * archetype.config.cruise_speed;
+                    world.entities.push(entities::spawn_archetype(archetype, sprites, *pos, vel));
+                },
+                InputEvent::RemoveNear { pos, radius } => world.entities.retain(|e| match (&e.transform, &e.config) {
+                    (Some(transform), Some(_config)) => transform.pos.distance(*pos) > *radius,
+                    _ => true
+                })
+            }
+        }
+        world.bounds = self.bounds;
+        world.step(self.elapsed);
+    }
+}
+
+pub struct Timeline {
+    keyframes: VecDeque<Snapshot>,
+    /// Input of every tick from the first keyframe on.
+    inputs: VecDeque<TickInput>
+}
+
+impl Timeline {
+    pub fn new(world: &World) -> Timeline {
+        Timeline {
+            keyframes: vec![world.snapshot()].into(),
+            inputs: VecDeque::new()
+        }
+    }
+
+    pub fn first_tick(&self) -> u64 {
+        self.keyframes.front().map(|k| k.tick).unwrap_or(0)
+    }
+
+    pub fn last_tick(&self) -> u64 {
+        self.first_tick() + self.inputs.len() as u64
+    }
+
+    /// Appends a tick run live, `world` being the state it resulted in.
+    pub fn record(&mut self, input: TickInput, world: &World) {
+        self.inputs.push_back(input);
+        if world.tick.is_multiple_of(KEYFRAME_INTERVAL) {
+            self.keyframes.push_back(world.snapshot());
+        }
+        if self.keyframes.len() > MAX_KEYFRAMES {
+            let dropped = self.keyframes.pop_front().map(|k| k.tick).unwrap_or(0);
+            self.inputs.drain(..(self.first_tick() - dropped) as usize);
+        }
+    }
+
+    /// Forgets everything after `tick`, so the run continues live from there.
+    pub fn truncate(&mut self, tick: u64) {
+        let tick = tick.max(self.first_tick());
+        self.inputs.truncate((tick - self.first_tick()) as usize);
+        self.keyframes.retain(|k| k.tick <= tick);
+    }
+
+    /// Rebuilds the world as it was at `tick`, clamped to the recorded range.
+    pub fn seek(&self, tick: u64, sprites: &HashMap<String, Sprite>) -> World {
+        let tick = tick.clamp(self.first_tick(), self.last_tick());
+        let keyframe = self.keyframes.iter().rev()
+            .find(|k| k.tick <= tick)
+            .unwrap_or(&self.keyframes[0]);
+
+        let mut world = World::from_snapshot(keyframe, sprites);
+        let first = self.first_tick();
+        for input in self.inputs.range((keyframe.tick - first) as usize..(tick - first) as usize) {
+            input.apply(&mut world, sprites);
+        }
+        world
+    }
+}
+
+pub fn timeline_hud_system(timeline: &Timeline, tick: u64) {
+    let (first, last) = (timeline.first_tick(), timeline.last_tick());
+    let (x, y, width) = (10.0, screen_height() - 30.0, screen_width() - 20.0);
+    let progress = if last > first { (tick - first) as f32 / (last - first) as f32 } else { 1.0 };
+
+    draw_rectangle(x, y, width, 6.0, Color::new(0.0, 0.0, 0.0, 0.5));
+    draw_rectangle(x, y, width * progress, 6.0, LIGHTGRAY);
+    draw_circle(x + width * progress, y + 3.0, 6.0, WHITE);
+    draw_text(&format!("tick {} / {}", tick, last), x, y - 6.0, 18.0, WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Scene;
+
+    #[test]
+    fn seeking_rebuilds_the_live_state() {
+        let mut world = World::new(&Scene { seed: Some(5), ..Scene::default() }, &HashMap::new());
+        let mut timeline = Timeline::new(&world);
+        let mut history = vec![];
+        for tick in 0..(KEYFRAME_INTERVAL + 50) {
+            let events = if tick == 30 { vec![InputEvent::DropFood(vec2(100.0, 100.0))] } else { vec![] };
+            let input = TickInput { elapsed: 1.0 / (50.0 + tick as f32), bounds: world.bounds, events };
+            input.apply(&mut world, &HashMap::new());
+            timeline.record(input, &world);
+            history.push(world.snapshot());
+        }
+
+        let positions = |world: &World| -> Vec<Option<Vec2>> {
+            world.entities.iter().map(|e| e.transform.as_ref().map(|t| t.pos)).collect()
+        };
+        for tick in [40, KEYFRAME_INTERVAL + 10] {
+            let expected = World::from_snapshot(&history[tick as usize - 1], &HashMap::new());
+            assert_eq!(positions(&timeline.seek(tick, &HashMap::new())), positions(&expected));
+        }
+    }
+}
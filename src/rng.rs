@@ -0,0 +1,112 @@
+//! The pseudo-random number generator of the simulation. It is the same PCG as `quad_rand`,
+//! but its state can be read back and restored by snapshots, and its ranges never reach their upper bound.
+
+use std::cell::Cell;
+
+const DEFAULT_INC: u64 = 1442695040888963407;
+const MULTIPLIER: u64 = 6364136223846793005;
+
+thread_local! {
+    static STATE: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn srand(seed: u64) {
+    set_state(0);
+    rand();
+    set_state(state().wrapping_add(seed));
+    rand();
+}
+
+pub fn state() -> u64 {
+    STATE.with(Cell::get)
+}
+
+pub fn set_state(state: u64) {
+    STATE.with(|s| s.set(state));
+}
+
+/// Returns a pseudo-random number between 0 and `u32::MAX`.
+pub fn rand() -> u32 {
+    let old = state();
+    set_state(old.wrapping_mul(MULTIPLIER).wrapping_add(DEFAULT_INC));
+    let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+    xorshifted.rotate_right((old >> 59) as u32)
+}
+
+/// A generator of its own, for draws that must neither depend on nor change what the simulation draws.
+/// Its state is swapped in for the duration of `draw`.
+pub struct Generator {
+    state: u64
+}
+
+impl Generator {
+    pub fn seeded(seed: u64) -> Generator {
+        let saved = state();
+        srand(seed);
+        let generator = Generator { state: state() };
+        set_state(saved);
+        generator
+    }
+
+    /// Runs `f`, whose calls to this module draw from this generator.
+    pub fn draw<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let saved = state();
+        set_state(self.state);
+        let result = f();
+        self.state = state();
+        set_state(saved);
+        result
+    }
+}
+
+/// Maps a number returned by `rand` to `[0, 1)`, in f64 so that `u32::MAX` stays below 1.
+fn unit(r: u32) -> f64 {
+    r as f64 / (u32::MAX as f64 + 1.0)
+}
+
+pub trait RandomRange {
+    fn gen_range(low: Self, high: Self) -> Self;
+}
+
+impl RandomRange for f32 {
+    fn gen_range(low: f32, high: f32) -> f32 {
+        (low as f64 + (high as f64 - low as f64) * unit(rand())) as f32
+    }
+}
+
+impl RandomRange for usize {
+    fn gen_range(low: usize, high: usize) -> usize {
+        (low as f64 + (high as f64 - low as f64) * unit(rand())) as usize
+    }
+}
+
+pub fn gen_range<T: RandomRange>(low: T, high: T) -> T {
+    T::gen_range(low, high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_exclude_their_upper_bound() {
+        assert_eq!(unit(0), 0.0);
+        assert!(unit(u32::MAX) < 1.0);
+        assert_eq!((3.0 * unit(u32::MAX)) as usize, 2);
+        srand(5);
+        assert!((0..10_000).all(|_i| gen_range(0, 3) < 3));
+    }
+
+    #[test]
+    fn generators_leave_the_global_state_alone() {
+        srand(5);
+        let expected: Vec<u32> = (0..3).map(|_i| rand()).collect();
+
+        srand(5);
+        let mut generator = Generator::seeded(5);
+        let drawn = generator.draw(|| (0..3).map(|_i| rand()).collect::<Vec<u32>>());
+        assert_eq!(drawn, expected);
+        assert_eq!((0..3).map(|_i| rand()).collect::<Vec<u32>>(), expected);
+        assert_ne!(generator.draw(rand), expected[0]);
+    }
+}
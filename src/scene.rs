@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::animator::Animator;
+use crate::boid;
+use crate::energy::Energy;
+use crate::food;
+use crate::food::{Feeder, Food};
+use crate::life::{Life, Reproduction};
+use crate::obstacle::Obstacle;
+use crate::predation::Predator;
+use crate::render::AnimationClip;
+
+/// Everything needed to populate a world, independent of textures so it can also run headless.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scene {
+    /// Seed of the random number generator, a random one is picked when missing.
+    pub seed: Option<u64>,
+    pub world_size: Vec2,
+    pub archetypes: Vec<Archetype>,
+    pub food: Vec<FoodSpawn>,
+    pub feeders: Vec<FeederSpawn>,
+    pub obstacles: Vec<ObstacleSpawn>,
+    /// Upper bound of the population of each flock, by flock id.
+    pub population_caps: HashMap<u8, usize>,
+    /// Clips replacing the built-in ones of a sprite, by sprite key. Frames are tiles of the sprite's spritesheet.
+    #[serde(default)]
+    pub clips: HashMap<String, Vec<AnimationClip>>
+}
+
+/// A kind of boid and how many of them are spawned at random positions.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Archetype {
+    pub name: String,
+    /// Key of the sprite used to draw this archetype.
+    pub sprite: String,
+    pub count: usize,
+    pub config: boid::Config,
+    /// Thresholds picking the clip of the sprite, which otherwise keeps its first clip.
+    pub animator: Option<Animator>,
+    pub energy: Option<Energy>,
+    pub predator: Option<Predator>,
+    pub life: Option<Life>,
+    pub reproduction: Option<Reproduction>
+}
+
+/// Food sources spawned at random positions.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FoodSpawn {
+    pub count: usize,
+    pub food: Food
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FeederSpawn {
+    pub pos: Vec2,
+    pub feeder: Feeder
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ObstacleSpawn {
+    pub pos: Vec2,
+    pub obstacle: Obstacle
+}
+
+const FISH_FLOCK_ID: u8 = 1;
+const SHARK_FLOCK_ID: u8 = 10;
+
+impl Default for Scene {
+    fn default() -> Self {
+        let world_size = vec2(1024.0, 768.0);
+        let bounds_margin = 120.0;
+        let fish_config = boid::Config {
+            bounds_margin,
+            neighborhood: boid::Neighborhood::Metric,
+            separation_perception: boid::Perception { distance: 20.0, field_of_view: -0.5 },
+            cohesion_perception: boid::Perception { distance: 200.0, field_of_view: -0.5 },
+            alignment_perception: boid::Perception { distance: 200.0, field_of_view: -0.5 },
+            avoidance_perception: boid::Perception { distance: 80.0, field_of_view: -1.0 },
+            hunt_perception: boid::Perception { distance: 0.0, field_of_view: 1.0 },
+            food_perception: boid::Perception { distance: 150.0, field_of_view: -0.5 },
+            separation_falloff: boid::Falloff::Constant,
+            avoidance_falloff: boid::Falloff::Constant,
+            separation_rule_weight: 0.75,
+            avoidance_rule_weight: 0.2,
+            cohesion_rule_weight: 0.01,
+            alignment_rule_weight: 0.03,
+            bounds_rule_weight: 0.02,
+            exploration_rule_weight: 0.04,
+            hunt_rule_weight: 0.0,
+            food_rule_weight: 0.02,
+            occlusion: None,
+            max_speed: 140.0,
+            cruise_speed: 110.0,
+            min_speed: 20.0,
+            max_force: 900.0,
+            max_turn_rate: 8.0,
+            flock_id: FISH_FLOCK_ID,
+            flock_to_avoid: vec![SHARK_FLOCK_ID].into_iter().collect(),
+            flock_to_hunt: Default::default()
+        };
+
+        let fish_archetype = Archetype {
+            name: "fish_0".to_string(),
+            sprite: "fish_0".to_string(),
+            count: 30,
+            config: fish_config.clone(),
+            animator: Some(Animator {
+                reference_speed: 100.0,
+                turn_rate: 3.0,
+                dash_speed: 125.0,
+                dash_acceleration: 400.0
+            }),
+            energy: Some(Energy {
+                current: 100.0,
+                max: 100.0,
+                base_drain: 0.5,
+                speed_drain: 4.0,
+                rest_recovery: 4.0,
+                rest_speed: 110.0,
+                exhausted_vigor: 0.5,
+                hungry_below: 0.8
+            }),
+            predator: None,
+            life: Some(Life {
+                age: 0.0,
+                lifespan: 90.0
+            }),
+            reproduction: Some(Reproduction {
+                energy_threshold: 0.85,
+                energy_cost: 0.3,
+                cooldown: 10.0,
+                timer: 0.0,
+                mutation: 0.05
+            })
+        };
+
+        let shark_archetype = Archetype {
+            name: "shark".to_string(),
+            sprite: "shark".to_string(),
+            count: 6,
+            config: boid::Config {
+                bounds_margin,
+                flock_id: SHARK_FLOCK_ID,
+                neighborhood: boid::Neighborhood::Metric,
+                separation_perception: boid::Perception { distance: 100.0, field_of_view: -0.9 },
+                cohesion_perception: boid::Perception { distance: 200.0, field_of_view: -0.9 },
+                alignment_perception: boid::Perception { distance: 200.0, field_of_view: -0.9 },
+                avoidance_perception: boid::Perception { distance: 100.0, field_of_view: -0.9 },
+                hunt_perception: boid::Perception { distance: 250.0, field_of_view: -0.5 },
+                food_perception: boid::Perception { distance: 0.0, field_of_view: 1.0 },
+                separation_falloff: boid::Falloff::Constant,
+                avoidance_falloff: boid::Falloff::Constant,
+                separation_rule_weight: 0.0,
+                avoidance_rule_weight: 0.0,
+                cohesion_rule_weight: 0.0,
+                alignment_rule_weight: 0.000,
+                bounds_rule_weight: 0.003,
+                exploration_rule_weight: 0.0015,
+                hunt_rule_weight: 0.02,
+                food_rule_weight: 0.0,
+                occlusion: None,
+                max_speed: 120.0,
+                cruise_speed: 50.0,
+                min_speed: 15.0,
+                max_force: 120.0,
+                max_turn_rate: 1.5,
+                flock_to_avoid: Default::default(),
+                flock_to_hunt: vec![FISH_FLOCK_ID].into_iter().collect()
+            },
+            animator: Some(Animator {
+                reference_speed: 100.0,
+                turn_rate: 1.0,
+                dash_speed: 90.0,
+                dash_acceleration: 100.0
+            }),
+            energy: Some(Energy {
+                current: 100.0,
+                max: 100.0,
+                base_drain: 0.5,
+                speed_drain: 8.0,
+                rest_recovery: 1.0,
+                rest_speed: 50.0,
+                exhausted_vigor: 0.4,
+                hungry_below: 0.5
+            }),
+            predator: Some(Predator {
+                catch_distance: 20.0,
+                energy_per_catch: 35.0
+            }),
+            life: Some(Life {
+                age: 0.0,
+                lifespan: 240.0
+            }),
+            reproduction: Some(Reproduction {
+                energy_threshold: 0.9,
+                energy_cost: 0.4,
+                cooldown: 30.0,
+                timer: 0.0,
+                mutation: 0.05
+            })
+        };
+
+        Scene {
+            seed: None,
+            world_size,
+            archetypes: vec![
+                fish_archetype.clone(),
+                Archetype { name: "fish_1".to_string(), sprite: "fish_1".to_string(), ..fish_archetype.clone() },
+                Archetype { name: "fish_2".to_string(), sprite: "fish_2".to_string(), ..fish_archetype.clone() },
+                Archetype { name: "fish_3".to_string(), sprite: "fish_3".to_string(), ..fish_archetype.clone() },
+                shark_archetype
+            ],
+            food: vec![FoodSpawn {
+                count: 4,
+                food: Food {
+                    amount: 40.0,
+                    max: 40.0,
+                    regrowth: 2.0,
+                    eat_rate: 10.0,
+                    radius: 16.0,
+                    color: DARKGREEN
+                }
+            }],
+            feeders: vec![FeederSpawn {
+                pos: world_size / 2.0,
+                feeder: Feeder {
+                    interval: 3.0,
+                    timer: 0.0,
+                    spread: 150.0,
+                    capacity: 5,
+                    food: food::pellet()
+                }
+            }],
+            obstacles: vec![],
+            population_caps: vec![(FISH_FLOCK_ID, 300), (SHARK_FLOCK_ID, 12)].into_iter().collect(),
+            clips: HashMap::new()
+        }
+    }
+}
+
+/// Serializes a `Color` as `[r, g, b, a]`.
+pub mod color {
+    use macroquad::prelude::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.r, color.g, color.b, color.a].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let [r, g, b, a] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(Color::new(r, g, b, a))
+    }
+}
@@ -0,0 +1,107 @@
+//! Compact encoding of a tuned scene for share links: the seed, the world size, and the count and
+//! config of every archetype, as JSON compressed with deflate and written in URL safe base64.
+//! Anything else (food, obstacles, sprites) comes from the scene the link is opened with.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use macroquad::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::boid;
+use crate::scene::Scene;
+
+// largest decompressed share accepted, far above any real scene
+const MAX_SIZE: usize = 1 << 20;
+
+#[derive(Serialize, Deserialize)]
+pub struct Share {
+    pub seed: u64,
+    /// Missing from codes shared before it was added, which keep the size of the scene.
+    #[serde(default)]
+    pub world_size: Option<Vec2>,
+    pub archetypes: Vec<SharedArchetype>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SharedArchetype {
+    pub name: String,
+    pub count: usize,
+    pub config: boid::Config
+}
+
+impl Share {
+    /// Captures the scene, which must have a seed to be reproduced.
+    pub fn capture(scene: &Scene) -> Option<Share> {
+        Some(Share {
+            seed: scene.seed?,
+            world_size: Some(scene.world_size),
+            archetypes: scene.archetypes.iter()
+                .map(|a| SharedArchetype { name: a.name.clone(), count: a.count, config: a.config.clone() })
+                .collect()
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("shares serialize");
+        URL_SAFE_NO_PAD.encode(miniz_oxide::deflate::compress_to_vec(&json, 9))
+    }
+
+    pub fn decode(code: &str) -> Result<Share, String> {
+        let compressed = URL_SAFE_NO_PAD.decode(code.trim()).map_err(|error| format!("invalid share code: {}", error))?;
+        let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_SIZE)
+            .map_err(|error| format!("invalid share code: {}", error))?;
+        serde_json::from_slice(&json).map_err(|error| format!("invalid share code: {}", error))
+    }
+
+    /// Sets the seed, the world size, and the count and config of the archetypes by name. The scene is left untouched
+    /// when it lacks one of the archetypes.
+    pub fn apply(&self, scene: &mut Scene) -> Result<(), String> {
+        if let Some(missing) = self.archetypes.iter().find(|shared| !scene.archetypes.iter().any(|a| a.name == shared.name)) {
+            return Err(format!("the share has an archetype {} the scene does not have", missing.name));
+        }
+        for shared in &self.archetypes {
+            if let Some(archetype) = scene.archetypes.iter_mut().find(|a| a.name == shared.name) {
+                archetype.count = shared.count;
+                archetype.config = shared.config.clone();
+            }
+        }
+        scene.seed = Some(self.seed);
+        if let Some(world_size) = self.world_size {
+            scene.world_size = world_size;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::vec2;
+
+    #[test]
+    fn shared_scene_survives_the_round_trip() {
+        let mut tuned = Scene { seed: Some(42), world_size: vec2(640.0, 480.0), ..Scene::default() };
+        tuned.archetypes[0].count = 7;
+        tuned.archetypes[0].config.cohesion_rule_weight = 0.125;
+        let code = Share::capture(&tuned).unwrap().encode();
+        assert!(code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let mut scene = Scene::default();
+        Share::decode(&code).unwrap().apply(&mut scene).unwrap();
+        assert_eq!(serde_json::to_value(&scene).unwrap(), serde_json::to_value(&tuned).unwrap());
+
+        assert!(Share::decode("not a share").is_err());
+
+        // a share the scene cannot take changes nothing
+        let mut share = Share::capture(&tuned).unwrap();
+        share.archetypes[1].name = "whale".to_string();
+        let mut scene = Scene::default();
+        assert!(share.apply(&mut scene).unwrap_err().contains("whale"));
+        assert_eq!(serde_json::to_value(&scene).unwrap(), serde_json::to_value(Scene::default()).unwrap());
+        assert!(Share::capture(&Scene::default()).is_none());
+
+        // codes shared before the world size keep the size of the scene
+        let share: Share = serde_json::from_str(r#"{"seed":1,"archetypes":[]}"#).unwrap();
+        assert!(share.world_size.is_none());
+    }
+}
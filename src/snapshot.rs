@@ -0,0 +1,139 @@
+//! Serializable copy of a whole world. Textures and animation definitions are not stored,
+//! entities refer to their sprite by key and get it back from the sprites they are restored with.
+
+use std::collections::HashMap;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::animator::{Animator, AnimatorState};
+use crate::boid;
+use crate::energy::Energy;
+use crate::entities::Entity;
+use crate::food::{Feeder, Food};
+use crate::life::{Life, Reproduction};
+use crate::movement::{Movement, Transform2D};
+use crate::obstacle::Obstacle;
+use crate::predation::Predator;
+use crate::render::Sprite;
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    /// State of `rng` when the snapshot was taken.
+    pub rng_state: u64,
+    pub next_id: u64,
+    /// Position and size of the world bounds.
+    pub bounds: (Vec2, Vec2),
+    pub population_caps: HashMap<u8, usize>,
+    pub entities: Vec<EntitySnapshot>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub id: Option<u64>,
+    pub transform: Option<Transform2D>,
+    pub movement: Option<Movement>,
+    pub config: Option<boid::Config>,
+    pub archetype: Option<String>,
+    pub sprite: Option<String>,
+    pub animation: Option<AnimationState>,
+    pub animator: Option<Animator>,
+    #[serde(default)]
+    pub animator_state: Option<AnimatorState>,
+    pub behavior: Option<boid::Behavior>,
+    pub obstacle: Option<Obstacle>,
+    pub energy: Option<Energy>,
+    pub predator: Option<Predator>,
+    pub food: Option<Food>,
+    pub feeder: Option<Feeder>,
+    pub life: Option<Life>,
+    pub reproduction: Option<Reproduction>
+}
+
+/// The progress of an animation, its definition being part of the sprite.
+#[derive(Serialize, Deserialize)]
+pub struct AnimationState {
+    /// Name of the clip playing, the first clip of the sprite when missing.
+    #[serde(default)]
+    pub clip: Option<String>,
+    pub tick: f32,
+    pub frame_number: usize,
+    pub speed: f32
+}
+
+impl EntitySnapshot {
+    pub fn capture(entity: &Entity) -> EntitySnapshot {
+        EntitySnapshot {
+            id: entity.id,
+            transform: entity.transform.clone(),
+            movement: entity.movement.clone(),
+            config: entity.config.clone(),
+            archetype: entity.archetype.clone(),
+            sprite: entity.sprite.clone(),
+            animation: entity.animation.as_ref().map(|a| AnimationState {
+                clip: a.clip_name().map(str::to_string),
+                tick: a.tick,
+                frame_number: a.frame_number,
+                speed: a.speed
+            }),
+            animator: entity.animator.clone(),
+            animator_state: entity.animator_state.clone(),
+            behavior: entity.behavior,
+            obstacle: entity.obstacle.clone(),
+            energy: entity.energy.clone(),
+            predator: entity.predator.clone(),
+            food: entity.food.clone(),
+            feeder: entity.feeder.clone(),
+            life: entity.life.clone(),
+            reproduction: entity.reproduction.clone()
+        }
+    }
+
+    /// Rebuilds the entity, without visuals when its sprite is missing from `sprites`.
+    pub fn restore(&self, sprites: &HashMap<String, Sprite>) -> Entity {
+        let sprite = self.sprite.as_ref().and_then(|key| sprites.get(key));
+        Entity {
+            id: self.id,
+            transform: self.transform.clone(),
+            movement: self.movement.clone(),
+            config: self.config.clone(),
+            archetype: self.archetype.clone(),
+            sprite: self.sprite.clone(),
+            animation: sprite.map(|s| match &self.animation {
+                Some(state) => {
+                    let mut animation = s.animation.clone();
+                    if let Some(clip) = &state.clip {
+                        animation.play(clip);
+                    }
+                    animation.tick = state.tick;
+                    animation.seek(state.frame_number);
+                    animation.speed = state.speed;
+                    animation
+                },
+                None => s.animation.clone()
+            }),
+            animator: self.animator.clone(),
+            animator_state: self.animator_state.clone(),
+            behavior: self.behavior,
+            renderable_texture: sprite.map(|s| s.renderable_texture.clone()),
+            obstacle: self.obstacle.clone(),
+            energy: self.energy.clone(),
+            predator: self.predator.clone(),
+            food: self.food.clone(),
+            feeder: self.feeder.clone(),
+            life: self.life.clone(),
+            reproduction: self.reproduction.clone()
+        }
+    }
+}
+
+pub fn save(path: &str, snapshot: &Snapshot) -> std::io::Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer(file, snapshot)?;
+    Ok(())
+}
+
+pub fn load(path: &str) -> std::io::Result<Snapshot> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
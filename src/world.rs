@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use macroquad::prelude::*;
+
+use crate::entities::Entity;
+use crate::render::Sprite;
+use crate::scene::Scene;
+use crate::snapshot::{EntitySnapshot, Snapshot};
+use crate::{animator, boid, energy, entities, food, life, movement, obstacle, polish, predation, rng};
+
+pub struct World {
+    pub entities: Vec<Entity>,
+    pub bounds: Rect,
+    pub population_caps: HashMap<u8, usize>,
+    /// Number of steps simulated so far.
+    pub tick: u64,
+    next_id: u64
+}
+
+impl World {
+    /// Seeds the random number generator and spawns the scene.
+    pub fn new(scene: &Scene, sprites: &HashMap<String, Sprite>) -> World {
+        rng::srand(scene.seed.unwrap_or_else(|| miniquad::date::now() as _));
+        let mut world = World {
+            entities: entities::spawn_scene(scene, sprites),
+            bounds: Rect::new(0.0, 0.0, scene.world_size.x, scene.world_size.y),
+            population_caps: scene.population_caps.clone(),
+            tick: 0,
+            next_id: 0
+        };
+        world.assign_ids();
+        world
+    }
+
+    /// Captures everything needed to resume the simulation exactly, including the random number generator.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tick: self.tick,
+            rng_state: rng::state(),
+            next_id: self.next_id,
+            bounds: (self.bounds.point(), self.bounds.size()),
+            population_caps: self.population_caps.clone(),
+            entities: self.entities.iter().map(EntitySnapshot::capture).collect()
+        }
+    }
+
+    /// Restores a snapshot, which also resets the random number generator to the state it was saved with.
+    pub fn from_snapshot(snapshot: &Snapshot, sprites: &HashMap<String, Sprite>) -> World {
+        rng::set_state(snapshot.rng_state);
+        let (pos, size) = snapshot.bounds;
+        World {
+            entities: snapshot.entities.iter().map(|e| e.restore(sprites)).collect(),
+            bounds: Rect::new(pos.x, pos.y, size.x, size.y),
+            population_caps: snapshot.population_caps.clone(),
+            tick: snapshot.tick,
+            next_id: snapshot.next_id
+        }
+    }
+
+    /// Advances the simulation by `elapsed` seconds. Input and rendering are left to the caller.
+    pub fn step(&mut self, elapsed: f32) {
+        let entities = &mut self.entities;
+        food::feeder_system(entities, elapsed);
+        food::food_regrowth_system(&mut entities::query_mut(entities), elapsed);
+        let obstacles = obstacle::obstacle_shapes(&entities::query(entities));
+        let foods = food::food_shapes(&entities::query(entities));
+        let mut boids = entities::query_mut(entities);
+        for index in boid::boids_system(&mut boids, &obstacles, &foods, &self.bounds, elapsed) {
+            match boids[index].id {
+                Some(id) => warn!("boid {} produced a non-finite velocity and was stopped", id),
+                None => warn!("a boid spawned this tick produced a non-finite velocity and was stopped")
+            }
+        }
+        movement::movement_system(&mut entities::query_mut(entities), elapsed);
+        energy::energy_system(&mut entities::query_mut(entities), elapsed);
+        predation::predation_system(entities);
+        food::feeding_system(entities, elapsed);
+        life::aging_system(entities, elapsed);
+        life::reproduction_system(entities, &self.population_caps, elapsed);
+        polish::facing_system(&mut entities::query_mut(entities));
+        animator::animator_system(&mut entities::query_mut(entities), elapsed);
+        self.assign_ids();
+        self.tick += 1;
+    }
+
+    /// Gives an id to every entity added since the last call, ids are never reused.
+    fn assign_ids(&mut self) {
+        for entity in self.entities.iter_mut().filter(|e| e.id.is_none()) {
+            entity.id = Some(self.next_id);
+            self.next_id += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restored_snapshot_resumes_the_same_run() {
+        let scene = Scene { seed: Some(3), ..Scene::default() };
+        let mut world = World::new(&scene, &HashMap::new());
+        for _ignored in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+        let json = serde_json::to_string(&world.snapshot()).unwrap();
+
+        for _ignored in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+        let mut restored = World::from_snapshot(&serde_json::from_str(&json).unwrap(), &HashMap::new());
+        for _ignored in 0..60 {
+            restored.step(1.0 / 60.0);
+        }
+
+        let positions = |world: &World| -> Vec<(Option<u64>, Option<Vec2>)> {
+            world.entities.iter().map(|e| (e.id, e.transform.as_ref().map(|t| t.pos))).collect()
+        };
+        assert_eq!(restored.tick, world.tick);
+        assert_eq!(positions(&restored), positions(&world));
+    }
+}